@@ -6,18 +6,20 @@ use rust_dbr::{
 use rust_dbr_macros::{fetch, DbrTable};
 //use dbr_sample_dataset::*;
 
-#[derive(DbrTable, Debug, Clone)]
+#[derive(DbrTable, sqlx::FromRow, Debug, Clone)]
 #[table = "ops.artist"]
+#[relation(Album)]
 pub struct Artist {
     id: i64,
     name: String,
 }
 
-#[derive(DbrTable, Debug, Clone)]
+#[derive(DbrTable, sqlx::FromRow, Debug, Clone)]
 #[table = "ops.album"]
+#[relation(Song)]
 pub struct Album {
     id: i64,
-    //#[relation(Artist)]
+    #[relation(Artist)]
     artist_id: i64,
     name: String,
     date_released: i64,
@@ -27,7 +29,7 @@ pub struct Album {
 #[table = "ops.song"]
 pub struct Song {
     id: i64,
-    //#[relation(Album)]
+    #[relation(Album)]
     album_id: i64,
     name: String,
     likes: i64,
@@ -39,12 +41,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     //let context = Context { client_id: None };
 
     /*
-    let artists: Vec<Active<Song>> = fetch!(&context, Artist where album.song.title like "%Baby%")?;
+    let artists: Vec<Active<Song>> = fetch!(&context, Artist where album.song.title like "%Baby%" prefetch albums.songs).await?;
     for artist in artists {
         println!("Artist: {}", artist.name()?);
-        for album in artist.albums().await {
+        for album in artist.albums(&context).await? {
             println!("\tAlbum: {}", album.name()?);
-            for song in album.songs().await {
+            for song in album.songs(&context).await? {
                 println!("\t\tSong: {}", song.name()?);
 
                 let new_name = song.name()?.replace("baby", "child");
@@ -69,11 +71,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         instances.insert(instance);
     }
 
-    let context = Context {
-        client_id: Some(1),
-        instances: instances,
-        metadata: metadata,
-    };
+    let context = Context::new(Some(1), instances, metadata);
 
     pub struct MyStruct {
         somethin: i64,
@@ -31,11 +31,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         instances.insert(instance);
     }
 
-    let context = Context {
-        client_id: Some(1),
-        instances: instances,
-        metadata: metadata,
-    };
+    let context = Context::new(Some(1), instances, metadata);
 
     let query = "";
 
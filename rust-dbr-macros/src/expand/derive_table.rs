@@ -1,9 +1,13 @@
 use proc_macro2::{Span, TokenStream};
 use quote::{format_ident, quote, ToTokens};
+use syn::ext::IdentExt;
 use syn::parse::Result;
-use syn::{Attribute, Data, Error, Fields, Lit, Meta, MetaNameValue, Type};
+use syn::{Attribute, Data, Error, Fields, Lit, Meta, MetaNameValue, NestedMeta, Path, Type};
 use syn::{DeriveInput, LitStr};
 
+use super::fetch::check::register_table;
+use super::relation::{is_option, relation_args, ToMany, ToOne};
+
 const TABLE_ATTRIBUTE_DESCRIPTOR: &'static str = "#[table = \"...\"]";
 
 fn table_name(attr: Attribute) -> Result<Option<LitStr>> {
@@ -23,10 +27,56 @@ fn table_name(attr: Attribute) -> Result<Option<LitStr>> {
     }
 }
 
+/// Column a field is read from, the `#[sqlx(rename = "..")]` if it has one, e.g. for `r#type`.
+fn column_name(field: &syn::Field) -> Result<LitStr> {
+    for attr in &field.attrs {
+        if !attr.path.is_ident("sqlx") {
+            continue;
+        }
+
+        if let Meta::List(list) = attr.parse_meta()? {
+            for nested in list.nested {
+                if let NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(rename),
+                    ..
+                })) = nested
+                {
+                    if path.is_ident("rename") {
+                        return Ok(rename);
+                    }
+                }
+            }
+        }
+    }
+
+    let ident = field.ident.as_ref().expect("field to have a name");
+    Ok(LitStr::new(&ident.unraw().to_string(), ident.span()))
+}
+
+/// `#[translate(Dollars)]` on a field, its getter and setter deal in the translated type instead.
+fn translator(field: &syn::Field) -> Result<Option<Path>> {
+    let mut translators = Vec::new();
+    for attr in &field.attrs {
+        if attr.path.is_ident("translate") {
+            translators.push(attr.parse_args::<Path>()?);
+        }
+    }
+
+    match translators.len() {
+        0 => Ok(None),
+        1 => Ok(Some(translators.remove(0))),
+        _ => Err(Error::new_spanned(field, "duplicate #[translate(..)]")),
+    }
+}
+
 pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
     let mut tables = Vec::new();
+    let mut to_many = Vec::new();
     for attr in input.attrs {
-        if let Some(name) = table_name(attr)? {
+        if let Some(args) = relation_args(&attr)? {
+            to_many.push(ToMany { args });
+        } else if let Some(name) = table_name(attr)? {
             tables.push(name)
         }
     }
@@ -52,6 +102,8 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
             None => return Err(syn::Error::new(Span::call_site(), format!("table name invalid: {}, expected \"schema_name.table_name\", e.g. \"ops.customer_order\"", table.value()))),
         };
 
+    register_table(&input.ident, &table.value());
+
     let data = match input.data {
         Data::Struct(data) => data,
         _ => {
@@ -79,12 +131,34 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
         let ty = partial_field.ty.to_token_stream();
         let wrapped_ty = quote! { Option<#ty> };
         partial_field.ty = Type::Verbatim(wrapped_ty);
+
+        // Our attributes (and anything for other derives) don't mean anything on the partial.
+        partial_field.attrs.retain(|attr| attr.path.is_ident("doc"));
+    }
+
+    let mut to_one = Vec::new();
+    for field in &named_fields {
+        if let Some(relation) = ToOne::from_field(field)? {
+            let table = relation.args.table.to_token_stream().to_string();
+            let duplicate = to_one
+                .iter()
+                .any(|existing: &ToOne| existing.args.table.to_token_stream().to_string() == table);
+            if duplicate {
+                return Err(Error::new_spanned(
+                    &relation.args.table,
+                    "only one relation per related table is supported",
+                ));
+            }
+
+            to_one.push(relation);
+        }
     }
 
     let vis = input.vis;
     let ident = input.ident.clone();
     let partial_ident = format_ident!("Partial{}", input.ident.clone());
     let fields_trait = format_ident!("{}Fields", input.ident.clone());
+    let prefetch_trait = format_ident!("{}Prefetch", input.ident.clone());
 
     let id_field = named_fields
         .iter()
@@ -98,6 +172,10 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
         .map(|field| field.ident.clone().expect("field to have name"))
         .collect();
     //let field_type: Vec<_> = named_fields.iter().map(|field| field.ty.clone()).collect();
+    let column_name = named_fields
+        .iter()
+        .map(column_name)
+        .collect::<Result<Vec<_>>>()?;
 
     let getter_fields: Vec<_> = named_fields
         .iter()
@@ -107,20 +185,97 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
         .iter()
         .map(|field| field.ident.clone().expect("field to have name"))
         .collect();
-    let getter_field_type: Vec<_> = getter_fields.iter().map(|field| field.ty.clone()).collect();
 
     let setter_fields = getter_fields.clone();
     let settable_field_name: Vec<_> = setter_fields
         .iter()
         .map(|field| field.ident.clone().expect("field to have a name"))
         .collect();
+    let settable_field_type: Vec<_> = setter_fields.iter().map(|field| field.ty.clone()).collect();
+    let settable_column_name = setter_fields
+        .iter()
+        .map(|field| self::column_name(field))
+        .collect::<Result<Vec<_>>>()?;
     let setter_field_fn: Vec<_> = setter_fields
         .iter()
-        .map(|field| format_ident!("set_{}", field.ident.clone().expect("field to have a name")))
+        .map(|field| {
+            format_ident!(
+                "set_{}",
+                field.ident.clone().expect("field to have a name").unraw()
+            )
+        })
+        .collect();
+
+    // Type the getter returns and the setter takes, along with how to get there from the field and back.
+    let mut accessor_type = Vec::new();
+    let mut getter_value = Vec::new();
+    let mut setter_value = Vec::new();
+    for field in &getter_fields {
+        let name = field.ident.clone().expect("field to have a name");
+        let ty = &field.ty;
+        match (translator(field)?, is_option(ty)) {
+            (None, _) => {
+                accessor_type.push(quote! { #ty });
+                getter_value.push(quote! { Ok(snapshot.#name) });
+                setter_value.push(quote! { #name });
+            }
+            (Some(translator), false) => {
+                accessor_type.push(quote! { #translator });
+                getter_value.push(quote! {
+                    ::rust_dbr::translator::from_field::<#translator, _>(snapshot.#name)
+                });
+                setter_value.push(quote! {
+                    ::rust_dbr::translator::into_field::<#translator, _>(#name)?
+                });
+            }
+            (Some(translator), true) => {
+                accessor_type.push(quote! { Option<#translator> });
+                getter_value.push(quote! {
+                    snapshot.#name
+                        .map(::rust_dbr::translator::from_field::<#translator, _>)
+                        .transpose()
+                });
+                setter_value.push(quote! {
+                    #name
+                        .map(::rust_dbr::translator::into_field::<#translator, _>)
+                        .transpose()?
+                });
+            }
+        }
+    }
+
+    let related_to_impls: Vec<_> = to_one
+        .iter()
+        .map(|relation| relation.related_to_tokens(&ident))
+        .collect();
+    let to_one_fn = to_one
+        .iter()
+        .map(|relation| relation.accessor())
+        .collect::<Result<Vec<_>>>()?;
+    let to_one_table: Vec<_> = to_one
+        .iter()
+        .map(|relation| relation.args.table.clone())
+        .collect();
+    let to_many_fn = to_many
+        .iter()
+        .map(|relation| relation.accessor())
+        .collect::<Result<Vec<_>>>()?;
+    let to_many_table: Vec<_> = to_many
+        .iter()
+        .map(|relation| relation.args.table.clone())
+        .collect();
+    let prefetch_to_one_fn: Vec<_> = to_one_fn
+        .iter()
+        .map(|accessor| format_ident!("prefetch_{}", accessor))
+        .collect();
+    let prefetch_to_many_fn: Vec<_> = to_many_fn
+        .iter()
+        .map(|accessor| format_ident!("prefetch_{}", accessor))
         .collect();
-    let setter_field_type: Vec<_> = setter_fields.iter().map(|field| field.ty.clone()).collect();
 
     let expanded = quote! {
+        #( #related_to_impls )*
+
         #[derive(Debug, Default, Clone)]
         #vis struct #partial_ident {
             #partial_fields
@@ -145,7 +300,73 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
                 Ok(())
             }
             fn id(&self) -> Option<<#ident as DbrTable>::Id> {
-                self.id
+                self.id.clone()
+            }
+            fn fields(&self) -> Vec<&'static str> {
+                let mut fields = Vec::new();
+                #(
+                    if self.#field_name.is_some() {
+                        fields.push(#column_name);
+                    }
+                )*
+
+                fields
+            }
+            fn from_record(record: &#ident, fields: &[&str]) -> Self {
+                let mut partial = Self::default();
+                #(
+                    if fields.contains(&#column_name) {
+                        partial.#field_name = Some(record.#field_name.clone());
+                    }
+                )*
+
+                partial
+            }
+            fn into_arguments(self) -> (Vec<&'static str>, ::rust_dbr::filter::BindValue) {
+                use ::sqlx::Arguments;
+
+                let mut fields = Vec::new();
+                let mut arguments = ::rust_dbr::filter::BindValue::default();
+                #(
+                    if let Some(value) = self.#field_name {
+                        fields.push(#column_name);
+                        arguments.add(value);
+                    }
+                )*
+
+                (fields, arguments)
+            }
+            fn field_values(&self) -> Vec<(&'static str, ::rust_dbr::validation::FieldValue)> {
+                #[allow(unused_imports)]
+                use ::rust_dbr::validation::{ViaToFieldValue, ViaUnchecked};
+
+                let mut values = Vec::new();
+                #(
+                    if let Some(value) = &self.#field_name {
+                        values.push((
+                            #column_name,
+                            (&::rust_dbr::validation::Probe(value)).field_value(),
+                        ));
+                    }
+                )*
+
+                values
+            }
+            fn version(field: &str, version: u64) -> Option<Self> {
+                #[allow(unused_imports)]
+                use ::rust_dbr::model::{ViaFromVersion, ViaNoVersion};
+
+                #(
+                    if field == #settable_column_name {
+                        let probe = ::rust_dbr::model::VersionProbe::<#settable_field_type>(::std::marker::PhantomData);
+                        return (&probe).from_version(version).map(|#settable_field_name| Self {
+                            #settable_field_name: Some(#settable_field_name),
+                            ..Default::default()
+                        });
+                    }
+                )*
+
+                None
             }
         }
 
@@ -154,6 +375,7 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
             type Id = #id_field_ty;
             type ActiveModel = ::rust_dbr::Active<#ident>;
             type PartialModel = #partial_ident;
+            const TABLE: &'static str = #table;
             fn schema() -> &'static str {
                 #schema
             }
@@ -161,20 +383,37 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
                 #table_name
             }
             fn fields() -> Vec<&'static str> {
-                vec![#(stringify!(#field_name)),*]
+                vec![#(#column_name),*]
+            }
+            fn id(&self) -> Self::Id {
+                self.id.clone()
             }
         }
 
         #[::async_trait::async_trait]
         #vis trait #fields_trait {
             #(
-                fn #getter_field_name(&self) -> Result<#getter_field_type, ::rust_dbr::DbrError>;
+                fn #getter_field_name(&self) -> Result<#accessor_type, ::rust_dbr::DbrError>;
             )*
 
             async fn set(&mut self, context: &::rust_dbr::Context, partial: #partial_ident) -> Result<(), DbrError>;
 
             #(
-                async fn #setter_field_fn<T: Into<#setter_field_type> + Send>(
+                async fn #to_one_fn(
+                    &self,
+                    context: &::rust_dbr::Context,
+                ) -> Result<Option<::rust_dbr::Active<#to_one_table>>, ::rust_dbr::DbrError>;
+            )*
+
+            #(
+                async fn #to_many_fn(
+                    &self,
+                    context: &::rust_dbr::Context,
+                ) -> Result<Vec<::rust_dbr::Active<#to_many_table>>, ::rust_dbr::DbrError>;
+            )*
+
+            #(
+                async fn #setter_field_fn<T: Into<#accessor_type> + Send>(
                     &mut self,
                     context: &::rust_dbr::Context,
                     #settable_field_name: T,
@@ -186,53 +425,49 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
         #[automatically_derived]
         impl #fields_trait for ::rust_dbr::Active<#ident> {
             #(
-                fn #getter_field_name(&self) -> Result<#getter_field_type, ::rust_dbr::DbrError> {
+                fn #getter_field_name(&self) -> Result<#accessor_type, ::rust_dbr::DbrError> {
                     let snapshot = self.snapshot()?;
-                    Ok(snapshot.#getter_field_name)
+                    #getter_value
                 }
             )*
 
             async fn set(&mut self, context: &Context, partial: #partial_ident) -> Result<(), ::rust_dbr::DbrError> {
-                use ::sqlx::Arguments;
-
-                let partial_clone = partial.clone();
-                let instance = context.instance_by_handle(#ident::schema().to_owned())?;
-                let mut fields: Vec<String> = Vec::new();
-                let mut query_str = String::new();
-                let mut arguments = ::sqlx::mysql::MySqlArguments::default();
-
-                #(
-                    if let Some(field) = partial.#settable_field_name {
-                        fields.push(format!("{} = ?", stringify!(#settable_field_name)));
-                        arguments.add(field);
-                    }
-                )*
-
-                if fields.len() == 0 {
-                    return Ok(())
+                if context.deferred_writes {
+                    self.stage(context, partial)
+                } else {
+                    self.update(context, partial).await
                 }
+            }
 
-                arguments.add(self.id());
-                query_str = format!("UPDATE {} SET {} WHERE id = ?", #ident::table_name(), fields.join(" "));
-
-                let query = ::sqlx::query_with(&query_str, arguments);
-                query.execute(&instance.pool).await?;
-
-                self.apply_partial(partial_clone)?;
+            #(
+                async fn #to_one_fn(
+                    &self,
+                    context: &::rust_dbr::Context,
+                ) -> Result<Option<::rust_dbr::Active<#to_one_table>>, ::rust_dbr::DbrError> {
+                    ::rust_dbr::relation::fetch_one::<#ident, #to_one_table>(context, self, stringify!(#to_one_fn)).await
+                }
+            )*
 
-                Ok(())
-            }
+            #(
+                async fn #to_many_fn(
+                    &self,
+                    context: &::rust_dbr::Context,
+                ) -> Result<Vec<::rust_dbr::Active<#to_many_table>>, ::rust_dbr::DbrError> {
+                    ::rust_dbr::relation::fetch_many::<#ident, #to_many_table>(context, self, stringify!(#to_many_fn)).await
+                }
+            )*
 
             #(
-                async fn #setter_field_fn<T: Into<#setter_field_type> + Send>(
+                async fn #setter_field_fn<T: Into<#accessor_type> + Send>(
                     &mut self,
                     context: &::rust_dbr::Context,
                     #settable_field_name: T,
                 ) -> Result<(), ::rust_dbr::DbrError> {
+                    let #settable_field_name: #accessor_type = #settable_field_name.into();
                     self.set(
                         context,
                         #partial_ident {
-                            #settable_field_name: Some(#settable_field_name.into()),
+                            #settable_field_name: Some(#setter_value),
                             ..Default::default()
                         },
                     )
@@ -241,6 +476,46 @@ pub fn dbr_table(input: DeriveInput) -> Result<TokenStream> {
                 }
             )*
         }
+
+        /// Batched loading of relations, used by `prefetch` in `fetch!`.
+        #[::async_trait::async_trait]
+        #vis trait #prefetch_trait {
+            #(
+                async fn #prefetch_to_one_fn(
+                    &self,
+                    context: &::rust_dbr::Context,
+                ) -> Result<Vec<::rust_dbr::Active<#to_one_table>>, ::rust_dbr::DbrError>;
+            )*
+
+            #(
+                async fn #prefetch_to_many_fn(
+                    &self,
+                    context: &::rust_dbr::Context,
+                ) -> Result<Vec<::rust_dbr::Active<#to_many_table>>, ::rust_dbr::DbrError>;
+            )*
+        }
+
+        #[::async_trait::async_trait]
+        #[automatically_derived]
+        impl #prefetch_trait for [::rust_dbr::Active<#ident>] {
+            #(
+                async fn #prefetch_to_one_fn(
+                    &self,
+                    context: &::rust_dbr::Context,
+                ) -> Result<Vec<::rust_dbr::Active<#to_one_table>>, ::rust_dbr::DbrError> {
+                    ::rust_dbr::relation::prefetch_one::<#ident, #to_one_table>(context, self, stringify!(#to_one_fn)).await
+                }
+            )*
+
+            #(
+                async fn #prefetch_to_many_fn(
+                    &self,
+                    context: &::rust_dbr::Context,
+                ) -> Result<Vec<::rust_dbr::Active<#to_many_table>>, ::rust_dbr::DbrError> {
+                    ::rust_dbr::relation::prefetch_many::<#ident, #to_many_table>(context, self, stringify!(#to_many_fn)).await
+                }
+            )*
+        }
     };
 
     Ok(TokenStream::from(expanded))
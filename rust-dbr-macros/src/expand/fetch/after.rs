@@ -0,0 +1,24 @@
+use syn::{
+    parse::{Parse, ParseStream},
+    Expr, Result,
+};
+
+use super::keyword;
+
+pub use super::prelude::*;
+
+/// `after cursor` for `fetch_page!`, the expression is an `Option<Cursor<T>>`
+#[derive(Debug, Clone)]
+pub struct AfterArgs {
+    pub after: keyword::after,
+    pub cursor_expr: Expr,
+}
+
+impl Parse for AfterArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let after = input.parse::<keyword::after>()?;
+        let cursor_expr = input.parse::<Expr>()?;
+
+        Ok(AfterArgs { after, cursor_expr })
+    }
+}
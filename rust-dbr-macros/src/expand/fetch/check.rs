@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use rust_dbr::metadata::{DataType, Field, Metadata, SchemaIdentifier, Table};
+use rust_dbr::DbrError;
+use syn::ext::IdentExt;
+use syn::{Error, Expr, ExprLit, Ident, Lit, Result, UnOp};
+
+pub use super::prelude::*;
+
+/// Metadata snapshot to check the fetch macros against, see `Metadata::save_to_path`
+///
+/// Relative paths are from the crate being compiled. Nothing is checked when this isn't set.
+pub const METADATA_ENV: &'static str = "RUST_DBR_METADATA";
+
+lazy_static::lazy_static! {
+    // The same process expands every macro of a crate (and sticks around for rust-analyzer),
+    // so only load the snapshot again once it has been modified.
+    static ref SNAPSHOTS: Mutex<HashMap<PathBuf, (Option<SystemTime>, std::result::Result<Arc<Metadata>, String>)>> =
+        Mutex::new(HashMap::new());
+
+    // `#[table = ".."]` of every struct the derive has expanded, by crate and struct name.
+    static ref TABLES: Mutex<HashMap<(String, String), String>> = Mutex::new(HashMap::new());
+}
+
+fn crate_name() -> String {
+    std::env::var("CARGO_CRATE_NAME").unwrap_or_default()
+}
+
+/// Remember the table a struct derives `DbrTable` for, see `table_for`
+pub fn register_table(ident: &Ident, table: &str) {
+    if let Ok(mut tables) = TABLES.lock() {
+        tables.insert((crate_name(), ident.unraw().to_string()), table.to_owned());
+    }
+}
+
+fn snapshot() -> Result<Option<Arc<Metadata>>> {
+    let path = match proc_macro::tracked_env::var(METADATA_ENV) {
+        Ok(path) if !path.is_empty() => PathBuf::from(path),
+        _ => return Ok(None),
+    };
+
+    let path = match std::env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) if path.is_relative() => PathBuf::from(manifest_dir).join(path),
+        _ => path,
+    };
+    proc_macro::tracked_path::path(path.to_string_lossy());
+
+    let modified = std::fs::metadata(&path)
+        .and_then(|file| file.modified())
+        .ok();
+
+    let mut snapshots = SNAPSHOTS
+        .lock()
+        .map_err(|_| Error::new(Span::call_site(), "metadata snapshot lock poisoned"))?;
+    let stale = match snapshots.get(&path) {
+        Some((loaded_at, _)) => *loaded_at != modified,
+        None => true,
+    };
+
+    if stale {
+        let loaded = Metadata::load_from_path(&path)
+            .map(Arc::new)
+            .map_err(|err| err.to_string());
+        snapshots.insert(path.clone(), (modified, loaded));
+    }
+
+    match &snapshots[&path].1 {
+        Ok(metadata) => Ok(Some(metadata.clone())),
+        Err(err) => Err(Error::new(
+            Span::call_site(),
+            format!(
+                "couldn't load metadata from {} (set by {}): {}",
+                path.display(),
+                METADATA_ENV,
+                err
+            ),
+        )),
+    }
+}
+
+/// Table of the struct's `#[table = ".."]`, along with that name.
+///
+/// The macros only see the struct name, so this goes by what the derive registered for it, which means
+/// the struct has to be declared in the same crate ahead of the fetches. Two structs of the same name
+/// are caught by the assertion from `check_arguments` instead.
+fn table_for<'m>(metadata: &'m Metadata, ident: &Ident) -> Result<(&'m Table, String)> {
+    let name = TABLES
+        .lock()
+        .map_err(|_| Error::new(Span::call_site(), "table registry lock poisoned"))?
+        .get(&(crate_name(), ident.unraw().to_string()))
+        .cloned();
+
+    let name = name.ok_or_else(|| {
+        Error::new_spanned(
+            ident,
+            format!(
+                "can't tell which table `{}` is for, with {} set it has to #[derive(DbrTable)] ahead of this in the same crate",
+                ident, METADATA_ENV
+            ),
+        )
+    })?;
+
+    let (schema, table) = name
+        .split_once('.')
+        .expect("derive to have checked the table name");
+    let table = metadata
+        .lookup_schema(SchemaIdentifier::Name(schema.to_owned()))
+        .and_then(|schema| schema.lookup_table_by_name(table.to_owned()))
+        .and_then(|table_id| metadata.lookup_table(*table_id))
+        .map_err(|err| Error::new_spanned(ident, format!("`{}`: {}", name, err)))?;
+
+    Ok((table, name))
+}
+
+/// Check the filter paths, `order by` keys and literal values against the metadata snapshot if there is one.
+///
+/// Evaluates to an assertion that `table` is the table that was checked, for the fetch to include.
+pub fn check_arguments(
+    table: &Ident,
+    filter: Option<&WhereArgs>,
+    order_by: Option<&OrderByArgs>,
+) -> Result<TokenStream> {
+    let metadata = match snapshot()? {
+        Some(metadata) => metadata,
+        None => return Ok(TokenStream::new()),
+    };
+
+    let (base_table, name) = table_for(&metadata, table)?;
+    let assertion = quote! {
+        const _: () = assert!(
+            ::rust_dbr::_same_table(<#table as ::rust_dbr::DbrTable>::TABLE, #name),
+            concat!("the fetch was checked against ", #name, ", another struct of the same name is for a different table"),
+        );
+    };
+
+    let mut errors = Vec::new();
+    if let Some(filter) = filter {
+        for predicate in filter.filter_tree.all_predicates() {
+            if let Err(err) = check_predicate(&metadata, base_table, predicate) {
+                errors.push(err);
+            }
+        }
+    }
+
+    if let Some(order_by) = order_by {
+        for key in &order_by.keys {
+            if let Err(err) = lookup_field(&metadata, base_table, &key.ident) {
+                errors.push(err);
+            }
+        }
+    }
+
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut combined) => {
+            for err in errors {
+                combined.combine(err);
+            }
+            Err(combined)
+        }
+        None => Ok(assertion),
+    }
+}
+
+fn metadata_error(err: DbrError) -> Error {
+    Error::new(Span::call_site(), err.to_string())
+}
+
+/// `expected one of a, b, c` for the error messages.
+fn one_of<'a, I: Iterator<Item = &'a String>>(names: I) -> String {
+    let mut names: Vec<_> = names.map(|name| format!("`{}`", name)).collect();
+    names.sort();
+    if names.is_empty() {
+        "there aren't any".to_owned()
+    } else {
+        format!("expected one of {}", names.join(", "))
+    }
+}
+
+fn lookup_field<'m>(metadata: &'m Metadata, table: &Table, ident: &Ident) -> Result<&'m Field> {
+    match table.fields.get(&ident.unraw().to_string()) {
+        Some(field_id) => metadata.lookup_field(*field_id).map_err(metadata_error),
+        None => Err(Error::new_spanned(
+            ident,
+            format!(
+                "unknown field `{}` on `{}`, {}",
+                ident,
+                table.name,
+                one_of(table.fields.keys())
+            ),
+        )),
+    }
+}
+
+fn check_predicate(
+    metadata: &Metadata,
+    base_table: &Table,
+    predicate: &FilterPredicate,
+) -> Result<()> {
+    let mut table = base_table;
+    for segment in predicate.path.relations() {
+        let name = segment.ident.unraw().to_string();
+        let relation_id = match table.relations.get(&name).and_then(|ids| ids.first()) {
+            Some(relation_id) => *relation_id,
+            None => {
+                return Err(Error::new_spanned(
+                    &segment.ident,
+                    format!(
+                        "unknown relation `{}` from `{}`, {}",
+                        name,
+                        table.name,
+                        one_of(table.relations.keys())
+                    ),
+                ))
+            }
+        };
+
+        let relation = metadata
+            .lookup_relation(relation_id)
+            .map_err(metadata_error)?;
+        table = metadata
+            .lookup_table(relation.to_table_id)
+            .map_err(metadata_error)?;
+    }
+
+    let field = lookup_field(metadata, table, &predicate.path.field().ident)?;
+
+    if let FilterOp::IsNull(is, _) = &predicate.op {
+        if !field.is_nullable {
+            is.span
+                .unwrap()
+                .warning(format!("`{}` is never null", field.name))
+                .emit();
+        }
+    }
+
+    let values: Vec<&Expr> = match &predicate.value {
+        FilterValue::None => Vec::new(),
+        FilterValue::Scalar(value) => vec![value],
+        FilterValue::Range { lower, upper, .. } => vec![lower, upper],
+        FilterValue::List(values) => list_items(values),
+    };
+
+    let mut errors = values
+        .into_iter()
+        .filter_map(|value| check_literal(field, value).err());
+    match errors.next() {
+        Some(mut combined) => {
+            for err in errors {
+                combined.combine(err);
+            }
+            Err(combined)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Items of `[a, b]` or `&[a, b]`, anything else we can't look into.
+fn list_items(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Array(array) => array.elems.iter().collect(),
+        Expr::Reference(reference) => list_items(&reference.expr),
+        Expr::Paren(paren) => list_items(&paren.expr),
+        Expr::Group(group) => list_items(&group.expr),
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LiteralKind {
+    Integer,
+    Float,
+    Text,
+    Bool,
+}
+
+fn literal_kind(expr: &Expr) -> Option<LiteralKind> {
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Int(_) => Some(LiteralKind::Integer),
+            Lit::Float(_) => Some(LiteralKind::Float),
+            Lit::Str(_) => Some(LiteralKind::Text),
+            Lit::Bool(_) => Some(LiteralKind::Bool),
+            _ => None,
+        },
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => literal_kind(&unary.expr),
+        Expr::Paren(paren) => literal_kind(&paren.expr),
+        Expr::Group(group) => literal_kind(&group.expr),
+        _ => None,
+    }
+}
+
+/// Only literals can be checked here, the types of anything else aren't known until later.
+fn check_literal(field: &Field, value: &Expr) -> Result<()> {
+    let (literal, kind) = match (literal_kind(value), field.kind()) {
+        (Some(literal), Some(kind)) => (literal, kind),
+        _ => return Ok(()),
+    };
+
+    let fits = match kind {
+        DataType::Bool => matches!(literal, LiteralKind::Bool | LiteralKind::Integer),
+        DataType::Float | DataType::Double | DataType::Decimal => {
+            matches!(literal, LiteralKind::Integer | LiteralKind::Float)
+        }
+        kind if kind.is_numeric() => literal == LiteralKind::Integer,
+        _ => literal == LiteralKind::Text,
+    };
+
+    let negative = matches!(value, Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)));
+    if fits && negative && kind.is_numeric() && !field.is_signed {
+        return Err(Error::new_spanned(
+            value,
+            format!("`{}` is unsigned", field.name),
+        ));
+    }
+
+    if fits {
+        return Ok(());
+    }
+
+    let literal = match literal {
+        LiteralKind::Integer => "an integer",
+        LiteralKind::Float => "a float",
+        LiteralKind::Text => "a string",
+        LiteralKind::Bool => "a bool",
+    };
+
+    Err(Error::new_spanned(
+        value,
+        format!(
+            "`{}` is a {:?} field, this is {}",
+            field.name, kind, literal
+        ),
+    ))
+}
@@ -0,0 +1,77 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    Expr, Ident, Result, Token,
+};
+
+pub use super::prelude::*;
+
+#[derive(Debug, Clone)]
+pub struct DeleteInput {
+    pub context: Expr,
+    pub comma: Token![,],
+    pub table: Ident,
+    pub filter: WhereArgs,
+}
+
+impl Parse for DeleteInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let context = input.parse::<Expr>()?;
+        let comma = input.parse::<Token![,]>()?;
+        let table = input.parse::<Ident>()?;
+
+        // Deleting without a filter is almost certainly a mistake, so make it required.
+        let filter = input.parse::<WhereArgs>()?;
+
+        Ok(DeleteInput {
+            context,
+            comma,
+            table,
+            filter,
+        })
+    }
+}
+
+pub fn delete(input: DeleteInput) -> Result<TokenStream> {
+    let table = input.table;
+    let context = input.context;
+    let table_assertion = check_arguments(&table, Some(&input.filter), None)?;
+
+    let base_table_tokens = quote! { __base_table_id };
+
+    let mut predicate_tests = Vec::new();
+    for predicate in input.filter.filter_tree.all_predicates() {
+        predicate_tests.push(predicate.value.assert_bindable_tokens());
+    }
+
+    let filter = input
+        .filter
+        .filter_tree
+        .as_filter_tree_tokens(&base_table_tokens);
+
+    let expanded = quote! {
+        async {
+            #table_assertion
+            #( #predicate_tests )*
+
+            let __context = #context;
+            let __schema = __context
+                .metadata
+                .lookup_schema(::rust_dbr::SchemaIdentifier::Name(#table::schema().to_owned()))?;
+            let __base_table_id = __schema.lookup_table_by_name(#table::table_name().to_owned())?;
+
+            let mut __select = ::rust_dbr::Select::new(*__base_table_id);
+            __select.filters = Some(#filter);
+
+            let __resolved_select = __select
+                .resolve(__context)?
+                .run_external_subqueries(__context)
+                .await?;
+
+            __resolved_select.delete::<#table>(__context).await
+        }
+    };
+
+    Ok(TokenStream::from(expanded))
+}
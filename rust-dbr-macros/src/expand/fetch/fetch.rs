@@ -1,9 +1,9 @@
-use proc_macro2::TokenStream;
-use quote::{quote, quote_spanned};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
     parse::{Parse, ParseStream},
     spanned::Spanned,
-    Expr, Ident, Result, Token,
+    Error, Expr, Ident, Result, Token,
 };
 
 pub use super::prelude::*;
@@ -35,6 +35,9 @@ pub struct FetchArguments {
     filter: Option<WhereArgs>,
     order_by: Option<OrderByArgs>,
     limit: Option<LimitArgs>,
+    offset: Option<OffsetArgs>,
+    after: Option<AfterArgs>,
+    prefetch: Option<PrefetchArgs>,
 }
 
 impl Parse for FetchArguments {
@@ -44,6 +47,9 @@ impl Parse for FetchArguments {
         let mut filter = None;
         let mut order_by = None;
         let mut limit = None;
+        let mut offset = None;
+        let mut after = None;
+        let mut prefetch = None;
 
         let lookahead = input.lookahead1();
         if lookahead.peek(Token![where]) {
@@ -60,41 +66,62 @@ impl Parse for FetchArguments {
             limit = Some(input.parse::<LimitArgs>()?);
         }
 
+        let lookahead = input.lookahead1();
+        if lookahead.peek(keyword::offset) {
+            offset = Some(input.parse::<OffsetArgs>()?);
+        }
+
+        let lookahead = input.lookahead1();
+        if lookahead.peek(keyword::after) {
+            after = Some(input.parse::<AfterArgs>()?);
+        }
+
+        let lookahead = input.lookahead1();
+        if lookahead.peek(keyword::prefetch) {
+            prefetch = Some(input.parse::<PrefetchArgs>()?);
+        }
+
         Ok(FetchArguments {
             table,
             filter,
             order_by,
             limit,
+            offset,
+            after,
+            prefetch,
         })
     }
 }
 
-pub fn fetch(input: FetchInput) -> Result<TokenStream> {
-    let table = input.arguments.table;
-    let context = input.context;
-    //let mut filter_path = Vec::new();
+/// Everything up until `__select` is ready to be resolved, shared between the fetch macros.
+///
+/// Also where the arguments get checked against the metadata snapshot, see `check_arguments`
+fn select_tokens(context: &Expr, arguments: &FetchArguments) -> Result<TokenStream> {
+    let table = &arguments.table;
+    let table_assertion = check_arguments(
+        table,
+        arguments.filter.as_ref(),
+        arguments.order_by.as_ref(),
+    )?;
 
     let base_table_tokens = quote! { __base_table_id };
 
     let mut predicate_tests = Vec::new();
 
-    let filter = match input.arguments.filter {
+    let filter = match &arguments.filter {
         Some(filter) => {
             let predicates = filter.filter_tree.all_predicates();
             for predicate in predicates {
-                let binding_value = &predicate.value;
-                predicate_tests.push(quote_spanned! { binding_value.span() =>
-                    ::rust_dbr::_assert_bindable(#binding_value);
-                });
+                predicate_tests.push(predicate.value.assert_bindable_tokens());
             }
 
             let tokens = filter.filter_tree.as_filter_tree_tokens(&base_table_tokens);
-            Some(tokens)
+            quote! { Some(#tokens) }
         }
-        None => None,
+        None => quote! { None },
     };
 
-    let order_by = if let Some(order) = input.arguments.order_by {
+    let order_by = if let Some(order) = &arguments.order_by {
         if let Some(tokens) = order.as_tokens() {
             quote! { __select.order = #tokens; }
         } else {
@@ -104,59 +131,322 @@ pub fn fetch(input: FetchInput) -> Result<TokenStream> {
         quote! {}
     };
 
-    let limit = if let Some(limit) = input.arguments.limit {
-        let limit_expr = limit.limit_expr;
-        let assert_bindable = quote_spanned! { limit_expr.span() =>
-            ::rust_dbr::_assert_bindable(#limit_expr);
-        };
+    Ok(quote! {
+        #table_assertion
+        #( #predicate_tests )*
+
+        let __context = #context;
+        use ::sqlx::Arguments;
+        let __instance = __context.instance_for::<#table>()?;
+
+        let __schema = __context
+            .metadata
+            .lookup_schema(::rust_dbr::SchemaIdentifier::Name(#table::schema().to_owned()))?;
+        let __base_table_id = __schema.lookup_table_by_name(#table::table_name().to_owned())?;
+        let __base_table = __context.metadata.lookup_table(*__base_table_id)?;
+
+        let mut __select = ::rust_dbr::Select::new(*__base_table_id);
+        __select.filters = #filter;
+        __select.fields = __base_table.fields.values().cloned().collect();
+        #order_by
+    })
+}
+
+/// `__select.limit = ...` or `__select.offset = ...`
+fn bind_select_scalar(field: Ident, expr: &Expr) -> TokenStream {
+    let assert_bindable = quote_spanned! { expr.span() =>
+        ::rust_dbr::_assert_bindable(#expr);
+    };
+
+    let arg_scalar = argument_scalar(quote! { #expr });
+    quote! {
+        #assert_bindable
+        __select.#field = Some(#arg_scalar);
+    }
+}
+
+/// Fetch the records into `active_records`, shared by `fetch!`, `fetch_one!` and `fetch_optional!`
+///
+/// `default_limit` is used when the macro wasn't given a `limit`.
+fn fetch_records_tokens(input: &FetchInput, default_limit: Option<usize>) -> Result<TokenStream> {
+    if let Some(after) = &input.arguments.after {
+        return Err(Error::new_spanned(
+            &after.after,
+            "`after` is only supported by `fetch_page!`",
+        ));
+    }
+
+    let table = &input.arguments.table;
+    let select = select_tokens(&input.context, &input.arguments)?;
 
-        let arg_scalar = argument_scalar(quote! { #limit_expr });
-        quote! {
-            #assert_bindable
-            __select.limit = Some(#arg_scalar);
+    let limit = match (&input.arguments.limit, default_limit) {
+        (Some(limit), _) => bind_select_scalar(format_ident!("limit"), &limit.limit_expr),
+        (None, Some(default_limit)) => {
+            let default_limit = default_limit as i64;
+            bind_select_scalar(
+                format_ident!("limit"),
+                &syn::parse_quote! { #default_limit },
+            )
         }
-    } else {
-        quote! {}
+        (None, None) => quote! {},
+    };
+
+    let offset = match &input.arguments.offset {
+        Some(offset) => bind_select_scalar(format_ident!("offset"), &offset.offset_expr),
+        None => quote! {},
     };
 
+    let prefetch = match &input.arguments.prefetch {
+        Some(prefetch) => prefetch.as_tokens(&format_ident!("active_records")),
+        None => quote! {},
+    };
+
+    Ok(quote! {
+        #select
+        #limit
+        #offset
+
+        let __resolved_select = __select
+            .resolve(__context)?
+            .run_external_subqueries(__context)
+            .await?;
+
+        let mut active_records: Vec<::rust_dbr::Active<#table>> = Vec::new();
+        if !__resolved_select.is_unsatisfiable() {
+            let (__sql, __args) = __resolved_select.as_sql()?;
+
+            // We have to capture the variables out here.
+            let __result_set: Vec<#table> = __context
+                .fetch_all_as(&__instance, &__sql, __args)
+                .await?;
+
+            for record in __result_set {
+                active_records.push(__context.register_record(&__instance, record)?);
+            }
+        }
+
+        #prefetch
+    })
+}
+
+pub fn fetch(input: FetchInput) -> Result<TokenStream> {
+    let table = &input.arguments.table;
+    let records = fetch_records_tokens(&input, None)?;
+
     // check that args are fine.
     let expanded = quote! {
         async {
-            #( #predicate_tests )*
-
-            let __context = #context;
-            use ::sqlx::Arguments;
-            let __instance = __context.instance_by_handle(#table::schema().to_owned())?;
-
-            let __schema = __context
-                .metadata
-                .lookup_schema(::rust_dbr::SchemaIdentifier::Name(#table::schema().to_owned()))?;
-            let __base_table_id = __schema.lookup_table_by_name(#table::table_name().to_owned())?;
-            let __base_table = __context.metadata.lookup_table(*__base_table_id)?;
-
-            let mut __select = ::rust_dbr::Select::new(*__base_table_id);
-            __select.filters = Some(#filter);
-            __select.fields = __base_table.fields.values().cloned().collect();
-            #order_by
+            #records
+
+            Ok::<Vec<::rust_dbr::Active<#table>>, ::rust_dbr::DbrError>(active_records)
+        }
+    };
+
+    Ok(TokenStream::from(expanded))
+}
+
+/// Exactly one record, `DbrError::RecordNotFound` or `DbrError::MultipleRecords` otherwise.
+pub fn fetch_one(input: FetchInput) -> Result<TokenStream> {
+    let table = &input.arguments.table;
+
+    // Only need to see a second record to know there is more than one.
+    let records = fetch_records_tokens(&input, Some(2))?;
+
+    let expanded = quote! {
+        async {
+            #records
+
+            let mut active_records = active_records;
+            match active_records.len() {
+                1 => Ok::<::rust_dbr::Active<#table>, ::rust_dbr::DbrError>(active_records.remove(0)),
+                0 => Err(::rust_dbr::DbrError::RecordNotFound {
+                    table: #table::table_name().to_owned(),
+                }),
+                _ => Err(::rust_dbr::DbrError::MultipleRecords {
+                    table: #table::table_name().to_owned(),
+                }),
+            }
+        }
+    };
+
+    Ok(TokenStream::from(expanded))
+}
+
+/// At most one record, `DbrError::MultipleRecords` if there are more.
+pub fn fetch_optional(input: FetchInput) -> Result<TokenStream> {
+    let table = &input.arguments.table;
+    let records = fetch_records_tokens(&input, Some(2))?;
+
+    let expanded = quote! {
+        async {
+            #records
+
+            let mut active_records = active_records;
+            match active_records.len() {
+                0 => Ok::<Option<::rust_dbr::Active<#table>>, ::rust_dbr::DbrError>(None),
+                1 => Ok(Some(active_records.remove(0))),
+                _ => Err(::rust_dbr::DbrError::MultipleRecords {
+                    table: #table::table_name().to_owned(),
+                }),
+            }
+        }
+    };
+
+    Ok(TokenStream::from(expanded))
+}
+
+/// `fetch_stream!(&context, Song where ...)`, evaluates to a `futures::Stream` of records.
+///
+/// See `Select::fetch_stream`
+pub fn fetch_stream(input: FetchInput) -> Result<TokenStream> {
+    if let Some(after) = &input.arguments.after {
+        return Err(Error::new_spanned(
+            &after.after,
+            "`after` is only supported by `fetch_page!`",
+        ));
+    }
+
+    if let Some(prefetch) = &input.arguments.prefetch {
+        return Err(Error::new_spanned(
+            &prefetch.prefetch,
+            "`prefetch` isn't supported on streams, the records aren't all there to batch up",
+        ));
+    }
+
+    let table = &input.arguments.table;
+    let select = select_tokens(&input.context, &input.arguments)?;
+
+    let limit = match &input.arguments.limit {
+        Some(limit) => bind_select_scalar(format_ident!("limit"), &limit.limit_expr),
+        None => quote! {},
+    };
+
+    let offset = match &input.arguments.offset {
+        Some(offset) => bind_select_scalar(format_ident!("offset"), &offset.offset_expr),
+        None => quote! {},
+    };
+
+    let expanded = quote! {
+        async {
+            #select
             #limit
+            #offset
 
-            let __resolved_select = __select.resolve(__context)?;
-            let (__sql, __args) = __resolved_select.as_sql()?;
+            __select.fetch_stream::<#table>(__context).await
+        }
+    };
 
-            dbg!(&__sql);
+    Ok(TokenStream::from(expanded))
+}
 
-            // We have to capture the variables out here.
-            let __result_set: Vec<#table> = ::sqlx::query_as_with(&__sql, __args)
-                .fetch_all(&__instance.pool).await?;
+/// `count!` and `exists!` only look at the filters.
+fn only_filters(arguments: &FetchArguments, name: &str) -> Result<()> {
+    let message = format!("`{}!` only takes a `where` clause", name);
+    if let Some(order_by) = &arguments.order_by {
+        return Err(Error::new_spanned(&order_by.order, message));
+    }
+    if let Some(limit) = &arguments.limit {
+        return Err(Error::new_spanned(&limit.limit, message));
+    }
+    if let Some(offset) = &arguments.offset {
+        return Err(Error::new_spanned(&offset.offset, message));
+    }
+    if let Some(after) = &arguments.after {
+        return Err(Error::new_spanned(&after.after, message));
+    }
+    if let Some(prefetch) = &arguments.prefetch {
+        return Err(Error::new_spanned(&prefetch.prefetch, message));
+    }
 
-            let mut active_records: Vec<::rust_dbr::Active<#table>> = Vec::new();
-            for record in __result_set {
-                let id = record.id;
-                let record_ref = __instance.cache.set_record(id, record)?;
-                active_records.push(::rust_dbr::Active::<#table>::from_arc(id, record_ref));
+    Ok(())
+}
+
+/// `count!(&context, Album where ...)`, evaluates to the number of matching records as an `i64`
+pub fn count(input: FetchInput) -> Result<TokenStream> {
+    only_filters(&input.arguments, "count")?;
+    let select = select_tokens(&input.context, &input.arguments)?;
+
+    let expanded = quote! {
+        async {
+            #select
+
+            __select.count(__context).await
+        }
+    };
+
+    Ok(TokenStream::from(expanded))
+}
+
+/// `exists!(&context, Album where ...)`, evaluates to whether anything matches.
+pub fn exists(input: FetchInput) -> Result<TokenStream> {
+    only_filters(&input.arguments, "exists")?;
+    let select = select_tokens(&input.context, &input.arguments)?;
+
+    let expanded = quote! {
+        async {
+            #select
+
+            __select.exists(__context).await
+        }
+    };
+
+    Ok(TokenStream::from(expanded))
+}
+
+/// `fetch_page!(&context, Album where ... order by name limit 20 after cursor)`
+///
+/// `limit` is the page size and `after` takes an `Option<Cursor<Album>>`, see `Select::fetch_page`
+pub fn fetch_page(input: FetchInput) -> Result<TokenStream> {
+    if let Some(offset) = &input.arguments.offset {
+        return Err(Error::new_spanned(
+            &offset.offset,
+            "`offset` can't be combined with a page cursor",
+        ));
+    }
+
+    let page_size = match &input.arguments.limit {
+        Some(limit) => &limit.limit_expr,
+        None => {
+            return Err(Error::new(
+                Span::call_site(),
+                "`fetch_page!` needs a `limit` for the page size",
+            ))
+        }
+    };
+
+    let table = &input.arguments.table;
+    let select = select_tokens(&input.context, &input.arguments)?;
+
+    let after = match &input.arguments.after {
+        Some(after) => {
+            let cursor_expr = &after.cursor_expr;
+            quote! { #cursor_expr }
+        }
+        None => quote! { None },
+    };
+
+    let prefetch = match &input.arguments.prefetch {
+        Some(prefetch) => {
+            let prefetch = prefetch.as_tokens(&format_ident!("active_records"));
+            quote! {
+                let active_records = &__page.records;
+                #prefetch
             }
+        }
+        None => quote! {},
+    };
 
-            Ok::<Vec<::rust_dbr::Active<#table>>, ::rust_dbr::DbrError>(active_records)
+    let expanded = quote! {
+        async {
+            #select
+
+            let __page = __select
+                .fetch_page::<#table>(__context, #page_size, #after)
+                .await?;
+
+            #prefetch
+
+            Ok::<::rust_dbr::Page<#table>, ::rust_dbr::DbrError>(__page)
         }
     };
 
@@ -7,6 +7,13 @@ syn::custom_keyword!(asc);
 syn::custom_keyword!(desc);
 
 syn::custom_keyword!(limit);
+syn::custom_keyword!(offset);
+syn::custom_keyword!(after);
+
+syn::custom_keyword!(prefetch);
 
 syn::custom_keyword!(like);
 syn::custom_keyword!(not);
+syn::custom_keyword!(between);
+syn::custom_keyword!(is);
+syn::custom_keyword!(null);
@@ -1,23 +1,49 @@
+pub mod after;
+pub mod check;
+pub mod delete;
 pub mod fetch;
 pub mod keyword;
 pub mod limit;
+pub mod offset;
 pub mod order_by;
+pub mod prefetch;
 pub mod r#where;
 
 pub use prelude::*;
 
 pub fn argument_scalar(stream: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     quote::quote! {
-        { use ::sqlx::Arguments; let mut args = ::sqlx::mysql::MySqlArguments::default(); args.add(#stream); args }
+        { use ::sqlx::Arguments; let mut args = ::rust_dbr::filter::BindValue::default(); args.add(#stream); args }
+    }
+}
+
+/// Bind every item of a collection, evaluates to the arguments and how many were bound.
+pub fn argument_list(stream: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote::quote! {
+        {
+            use ::sqlx::Arguments;
+            let mut args = ::rust_dbr::filter::BindValue::default();
+            let mut len = 0usize;
+            for value in #stream {
+                args.add(value);
+                len += 1;
+            }
+            (args, len)
+        }
     }
 }
 
 mod prelude {
-    pub use super::argument_scalar;
+    pub use super::{argument_list, argument_scalar};
 
+    pub use super::after::*;
+    pub use super::check::*;
+    pub use super::delete::*;
     pub use super::fetch::*;
     pub use super::keyword;
     pub use super::limit::*;
+    pub use super::offset::*;
     pub use super::order_by::*;
+    pub use super::prefetch::*;
     pub use super::r#where::*;
 }
@@ -0,0 +1,26 @@
+use syn::{
+    parse::{Parse, ParseStream},
+    Expr, Result,
+};
+
+use super::keyword;
+
+pub use super::prelude::*;
+
+#[derive(Debug, Clone)]
+pub struct OffsetArgs {
+    pub offset: keyword::offset,
+    pub offset_expr: Expr,
+}
+
+impl Parse for OffsetArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let offset = input.parse::<keyword::offset>()?;
+        let offset_expr = input.parse::<Expr>()?;
+
+        Ok(OffsetArgs {
+            offset,
+            offset_expr,
+        })
+    }
+}
@@ -1,6 +1,7 @@
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{
+    ext::IdentExt,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
     Ident, Result, Token,
@@ -38,7 +39,7 @@ impl OrderByArgs {
         let key = self
             .keys
             .iter()
-            .map(|key| key.ident.to_string())
+            .map(|key| key.ident.unraw().to_string())
             .collect::<Vec<_>>();
         let direction = self
             .keys
@@ -0,0 +1,93 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, Result, Token,
+};
+
+use super::keyword;
+
+pub use super::prelude::*;
+
+/// `prefetch album.artist, album.songs`
+#[derive(Debug, Clone)]
+pub struct PrefetchArgs {
+    pub prefetch: keyword::prefetch,
+    pub paths: Punctuated<FilterPath, Token![,]>,
+}
+
+impl Parse for PrefetchArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let prefetch = input.parse::<keyword::prefetch>()?;
+        let paths = Punctuated::<FilterPath, Token![,]>::parse_separated_nonempty(input)?;
+
+        Ok(PrefetchArgs { prefetch, paths })
+    }
+}
+
+/// One hop of a prefetch, paths sharing a prefix share the hop so it is only loaded once.
+#[derive(Debug, Clone)]
+struct PrefetchNode {
+    relation: Ident,
+    children: Vec<PrefetchNode>,
+}
+
+impl PrefetchNode {
+    fn insert(nodes: &mut Vec<PrefetchNode>, path: &[Ident]) {
+        let (relation, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+
+        let index = match nodes.iter().position(|node| &node.relation == relation) {
+            Some(index) => index,
+            None => {
+                nodes.push(PrefetchNode {
+                    relation: relation.clone(),
+                    children: Vec::new(),
+                });
+                nodes.len() - 1
+            }
+        };
+
+        Self::insert(&mut nodes[index].children, rest);
+    }
+
+    fn as_tokens(nodes: &[PrefetchNode], records: &Ident, counter: &mut usize) -> TokenStream {
+        let mut tokens = TokenStream::new();
+        for node in nodes {
+            let related = format_ident!("__prefetched_{}", *counter);
+            *counter += 1;
+
+            let prefetch_fn = format_ident!("prefetch_{}", node.relation);
+            let children = Self::as_tokens(&node.children, &related, counter);
+            tokens.extend(quote! {
+                let #related = #records.#prefetch_fn(__context).await?;
+                #children
+            });
+        }
+
+        tokens
+    }
+}
+
+impl PrefetchArgs {
+    /// Batched loads for every hop of every path, one query per hop.
+    ///
+    /// The generated `{Table}Prefetch` traits need to be in scope.
+    pub fn as_tokens(&self, records: &Ident) -> TokenStream {
+        let mut nodes = Vec::new();
+        for path in &self.paths {
+            let path = path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.clone())
+                .collect::<Vec<_>>();
+            PrefetchNode::insert(&mut nodes, &path);
+        }
+
+        let mut counter = 0;
+        PrefetchNode::as_tokens(&nodes, records, &mut counter)
+    }
+}
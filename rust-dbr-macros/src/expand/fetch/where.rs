@@ -1,8 +1,10 @@
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
 use syn::{
+    ext::IdentExt,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
+    spanned::Spanned,
     token, Expr, Ident, Result, Token,
 };
 
@@ -210,7 +212,7 @@ impl FilterPath {
     pub fn relations_str(&self) -> Vec<String> {
         self.relations()
             .iter()
-            .map(|relation| relation.ident.to_string())
+            .map(|relation| relation.ident.unraw().to_string())
             .collect()
     }
 
@@ -220,7 +222,7 @@ impl FilterPath {
     }
 
     pub fn field_str(&self) -> String {
-        self.field().ident.to_string()
+        self.field().ident.unraw().to_string()
     }
 
     pub fn as_relation_path_tokens(&self, base_table_expr: &TokenStream) -> TokenStream {
@@ -240,14 +242,23 @@ impl FilterPath {
 pub struct FilterPredicate {
     pub path: FilterPath,
     pub op: FilterOp,
-    pub value: Expr,
+    pub value: FilterValue,
 }
 
 impl Parse for FilterPredicate {
     fn parse(input: ParseStream) -> Result<Self> {
         let path = input.parse::<FilterPath>()?;
         let op = input.parse::<FilterOp>()?;
-        let value = input.parse::<Expr>()?;
+        let value = match op {
+            FilterOp::IsNull(..) | FilterOp::IsNotNull(..) => FilterValue::None,
+            FilterOp::Between(_) => FilterValue::Range {
+                lower: input.parse()?,
+                and: input.parse()?,
+                upper: input.parse()?,
+            },
+            FilterOp::In(_) | FilterOp::NotIn(..) => FilterValue::List(input.parse()?),
+            _ => FilterValue::Scalar(input.parse()?),
+        };
 
         Ok(Self { path, op, value })
     }
@@ -257,14 +268,82 @@ impl FilterPredicate {
     pub fn as_filter_tokens(&self, base_table_expr: &TokenStream) -> TokenStream {
         let op_tokens = self.op.as_tokens();
         let path_tokens = self.path.as_relation_path_tokens(base_table_expr);
-        let value_tokens = &self.value;
-        let arg_scalar = argument_scalar(quote! { #value_tokens });
+        let value_tokens = self.value.as_tokens();
         quote! {
             ::rust_dbr::FilterPredicate {
                 path: #path_tokens,
                 op: #op_tokens,
-                value: #arg_scalar,
+                value: #value_tokens,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum FilterValue {
+    None,
+    Scalar(Expr),
+    Range {
+        lower: Expr,
+        and: keyword::and,
+        upper: Expr,
+    },
+    List(Expr),
+}
+
+impl FilterValue {
+    pub fn as_tokens(&self) -> TokenStream {
+        match self {
+            Self::None => quote! { ::rust_dbr::FilterValue::None },
+            Self::Scalar(value) => {
+                let arg_scalar = argument_scalar(quote! { #value });
+                quote! { ::rust_dbr::FilterValue::Scalar(#arg_scalar) }
+            }
+            Self::Range { lower, upper, .. } => {
+                quote! {
+                    ::rust_dbr::FilterValue::Range({
+                        use ::sqlx::Arguments;
+                        let mut args = ::rust_dbr::filter::BindValue::default();
+                        args.add(#lower);
+                        args.add(#upper);
+                        args
+                    })
+                }
+            }
+            Self::List(values) => {
+                let arg_list = argument_list(quote! { #values });
+                quote! {
+                    {
+                        let (values, len) = #arg_list;
+                        ::rust_dbr::FilterValue::List { values, len }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check that the values can be bound, spanned to each value.
+    pub fn assert_bindable_tokens(&self) -> TokenStream {
+        match self {
+            Self::None => quote! {},
+            Self::Scalar(value) => quote_spanned! { value.span() =>
+                ::rust_dbr::_assert_bindable(#value);
+            },
+            Self::Range { lower, upper, .. } => {
+                let lower_tokens = quote_spanned! { lower.span() =>
+                    ::rust_dbr::_assert_bindable(#lower);
+                };
+                let upper_tokens = quote_spanned! { upper.span() =>
+                    ::rust_dbr::_assert_bindable(#upper);
+                };
+                quote! {
+                    #lower_tokens
+                    #upper_tokens
+                }
             }
+            Self::List(values) => quote_spanned! { values.span() =>
+                ::rust_dbr::_assert_bindable_list(&#values);
+            },
         }
     }
 }
@@ -275,6 +354,15 @@ pub enum FilterOp {
     NotEq(Token![!=]),
     Like(keyword::like),
     NotLike(keyword::not, keyword::like),
+    Lt(Token![<]),
+    LtEq(Token![<=]),
+    Gt(Token![>]),
+    GtEq(Token![>=]),
+    Between(keyword::between),
+    In(Token![in]),
+    NotIn(keyword::not, Token![in]),
+    IsNull(keyword::is, keyword::null),
+    IsNotNull(keyword::is, keyword::not, keyword::null),
 }
 
 impl FilterOp {
@@ -284,6 +372,15 @@ impl FilterOp {
             Self::NotEq(_) => quote! { ::rust_dbr::FilterOp::NotEq },
             Self::Like(_) => quote! { ::rust_dbr::FilterOp::Like },
             Self::NotLike(_, _) => quote! { ::rust_dbr::FilterOp::NotLike },
+            Self::Lt(_) => quote! { ::rust_dbr::FilterOp::Lt },
+            Self::LtEq(_) => quote! { ::rust_dbr::FilterOp::LtEq },
+            Self::Gt(_) => quote! { ::rust_dbr::FilterOp::Gt },
+            Self::GtEq(_) => quote! { ::rust_dbr::FilterOp::GtEq },
+            Self::Between(_) => quote! { ::rust_dbr::FilterOp::Between },
+            Self::In(_) => quote! { ::rust_dbr::FilterOp::In },
+            Self::NotIn(_, _) => quote! { ::rust_dbr::FilterOp::NotIn },
+            Self::IsNull(_, _) => quote! { ::rust_dbr::FilterOp::IsNull },
+            Self::IsNotNull(_, _, _) => quote! { ::rust_dbr::FilterOp::IsNotNull },
         }
     }
 }
@@ -297,13 +394,52 @@ impl Parse for FilterOp {
         } else if lookahead.peek(Token![!=]) {
             let neq = input.parse::<Token![!=]>()?;
             Ok(FilterOp::NotEq(neq))
+        } else if lookahead.peek(Token![<=]) {
+            let lt_eq = input.parse::<Token![<=]>()?;
+            Ok(FilterOp::LtEq(lt_eq))
+        } else if lookahead.peek(Token![<]) {
+            let lt = input.parse::<Token![<]>()?;
+            Ok(FilterOp::Lt(lt))
+        } else if lookahead.peek(Token![>=]) {
+            let gt_eq = input.parse::<Token![>=]>()?;
+            Ok(FilterOp::GtEq(gt_eq))
+        } else if lookahead.peek(Token![>]) {
+            let gt = input.parse::<Token![>]>()?;
+            Ok(FilterOp::Gt(gt))
         } else if lookahead.peek(keyword::like) {
             let like = input.parse::<keyword::like>()?;
             Ok(FilterOp::Like(like))
+        } else if lookahead.peek(keyword::between) {
+            let between = input.parse::<keyword::between>()?;
+            Ok(FilterOp::Between(between))
+        } else if lookahead.peek(Token![in]) {
+            let r#in = input.parse::<Token![in]>()?;
+            Ok(FilterOp::In(r#in))
+        } else if lookahead.peek(keyword::is) {
+            let is = input.parse::<keyword::is>()?;
+            let lookahead = input.lookahead1();
+            if lookahead.peek(keyword::not) {
+                let not = input.parse::<keyword::not>()?;
+                let null = input.parse::<keyword::null>()?;
+                Ok(FilterOp::IsNotNull(is, not, null))
+            } else if lookahead.peek(keyword::null) {
+                let null = input.parse::<keyword::null>()?;
+                Ok(FilterOp::IsNull(is, null))
+            } else {
+                Err(lookahead.error())
+            }
         } else if lookahead.peek(keyword::not) {
             let not = input.parse::<keyword::not>()?;
-            let like = input.parse::<keyword::like>()?;
-            Ok(FilterOp::NotLike(not, like))
+            let lookahead = input.lookahead1();
+            if lookahead.peek(keyword::like) {
+                let like = input.parse::<keyword::like>()?;
+                Ok(FilterOp::NotLike(not, like))
+            } else if lookahead.peek(Token![in]) {
+                let r#in = input.parse::<Token![in]>()?;
+                Ok(FilterOp::NotIn(not, r#in))
+            } else {
+                Err(lookahead.error())
+            }
         } else {
             Err(lookahead.error())
         }
@@ -1,2 +1,3 @@
 pub mod derive_table;
 pub mod fetch;
+pub mod relation;
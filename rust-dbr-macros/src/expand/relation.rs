@@ -0,0 +1,180 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    Attribute, Error, Field, GenericArgument, Ident, Path, PathArguments, Result, Token, Type,
+};
+
+const RELATION_ATTRIBUTE_DESCRIPTOR: &'static str =
+    "#[relation(Table)] or #[relation(Table as name)]";
+
+/// `#[relation(Artist)]`, `#[relation(Album as records)]`
+#[derive(Debug, Clone)]
+pub struct RelationArgs {
+    pub table: Path,
+    pub alias: Option<Ident>,
+}
+
+impl Parse for RelationArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let table = input.parse::<Path>()?;
+        let alias = if input.peek(Token![as]) {
+            input.parse::<Token![as]>()?;
+            Some(input.parse::<Ident>()?)
+        } else {
+            None
+        };
+
+        Ok(RelationArgs { table, alias })
+    }
+}
+
+pub fn relation_args(attr: &Attribute) -> Result<Option<RelationArgs>> {
+    if !attr.path.is_ident("relation") {
+        return Ok(None);
+    }
+
+    attr.parse_args::<RelationArgs>().map(Some).map_err(|err| {
+        Error::new(
+            err.span(),
+            format!("{}, expected {}", err, RELATION_ATTRIBUTE_DESCRIPTOR),
+        )
+    })
+}
+
+/// To-one relation declared on the field holding the foreign key.
+///
+/// ```ignore
+/// #[relation(Artist)]
+/// artist_id: i64,
+/// ```
+#[derive(Debug, Clone)]
+pub struct ToOne {
+    pub field: Ident,
+    pub optional: bool,
+    pub args: RelationArgs,
+}
+
+impl ToOne {
+    pub fn from_field(field: &Field) -> Result<Option<Self>> {
+        let mut relations = Vec::new();
+        for attr in &field.attrs {
+            if let Some(args) = relation_args(attr)? {
+                relations.push((attr, args));
+            }
+        }
+
+        match relations.len() {
+            0 => Ok(None),
+            1 => {
+                let (_, args) = relations.remove(0);
+                let field_ident = field.ident.clone().expect("field to have a name");
+                Ok(Some(ToOne {
+                    field: field_ident,
+                    optional: is_option(&field.ty),
+                    args,
+                }))
+            }
+            _ => Err(Error::new_spanned(
+                relations[1].0,
+                "a field can only have one relation",
+            )),
+        }
+    }
+
+    /// `artist_id` -> `artist`, unless an alias is given.
+    pub fn accessor(&self) -> Result<Ident> {
+        if let Some(alias) = &self.args.alias {
+            return Ok(alias.clone());
+        }
+
+        match self.field.unraw().to_string().strip_suffix("_id") {
+            Some(name) if name.len() > 0 => Ok(format_ident!("{}", name)),
+            _ => Err(Error::new_spanned(
+                &self.field,
+                "can't name the relation from a field that doesn't end in `_id`, use #[relation(Table as name)]",
+            )),
+        }
+    }
+
+    pub fn related_to_tokens(&self, ident: &Ident) -> TokenStream {
+        let table = &self.args.table;
+        let field = &self.field;
+        let foreign_key = if self.optional {
+            quote! { self.#field.clone().map(Into::into) }
+        } else {
+            quote! { Some(self.#field.clone().into()) }
+        };
+
+        quote! {
+            #[automatically_derived]
+            impl ::rust_dbr::RelatedTo<#table> for #ident {
+                fn foreign_key(&self) -> Option<<#table as ::rust_dbr::DbrTable>::Id> {
+                    #foreign_key
+                }
+            }
+        }
+    }
+}
+
+/// To-many relation declared on the struct, the related table has a to-one relation back to us.
+///
+/// ```ignore
+/// #[relation(Album)]
+/// struct Artist { .. }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ToMany {
+    pub args: RelationArgs,
+}
+
+impl ToMany {
+    /// `Album` -> `albums`, unless an alias is given.
+    pub fn accessor(&self) -> Result<Ident> {
+        if let Some(alias) = &self.args.alias {
+            return Ok(alias.clone());
+        }
+
+        match self.args.table.segments.last() {
+            Some(segment) => Ok(format_ident!("{}s", snake_case(&segment.ident.to_string()))),
+            None => Err(Error::new_spanned(
+                &self.args.table,
+                format!("expected {}", RELATION_ATTRIBUTE_DESCRIPTOR),
+            )),
+        }
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, character) in name.chars().enumerate() {
+        if character.is_uppercase() {
+            if index > 0 {
+                snake.push('_');
+            }
+            snake.extend(character.to_lowercase());
+        } else {
+            snake.push(character);
+        }
+    }
+
+    snake
+}
+
+pub fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => match path.path.segments.last() {
+            Some(segment) => {
+                segment.ident == "Option"
+                    && matches!(
+                        &segment.arguments,
+                        PathArguments::AngleBracketed(arguments)
+                            if matches!(arguments.args.first(), Some(GenericArgument::Type(_)))
+                    )
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
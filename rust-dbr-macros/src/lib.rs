@@ -1,10 +1,10 @@
-#![feature(proc_macro_diagnostic)]
+#![feature(proc_macro_diagnostic, proc_macro_tracked_env, track_path)]
 
 use syn::{parse_macro_input, DeriveInput};
 
 mod expand;
 
-#[proc_macro_derive(DbrTable, attributes(table, relation))]
+#[proc_macro_derive(DbrTable, attributes(table, relation, translate))]
 pub fn dbr(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     expand::derive_table::dbr_table(input)
@@ -19,3 +19,59 @@ pub fn fetch(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         .unwrap_or_else(syn::Error::into_compile_error)
         .into()
 }
+
+#[proc_macro]
+pub fn fetch_one(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as expand::fetch::FetchInput);
+    expand::fetch::fetch_one(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+pub fn fetch_optional(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as expand::fetch::FetchInput);
+    expand::fetch::fetch_optional(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+pub fn count(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as expand::fetch::FetchInput);
+    expand::fetch::count(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+pub fn exists(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as expand::fetch::FetchInput);
+    expand::fetch::exists(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+pub fn fetch_stream(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as expand::fetch::FetchInput);
+    expand::fetch::fetch_stream(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+pub fn fetch_page(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as expand::fetch::FetchInput);
+    expand::fetch::fetch_page(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+pub fn delete(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as expand::fetch::DeleteInput);
+    expand::fetch::delete(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
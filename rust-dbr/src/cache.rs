@@ -1,13 +1,16 @@
 use crate::prelude::*;
 use std::{
     any::{Any, TypeId},
-    collections::{btree_map::Entry, BTreeMap, HashMap},
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap},
     ops::{Deref, DerefMut},
     sync::{Arc, Mutex, RwLock, Weak},
 };
 
 pub type Store<T> = BTreeMap<<T as DbrTable>::Id, Weak<Mutex<RecordMetadata<T>>>>;
 
+/// Prefetched records of one relation, see `RecordMetadata::relations`
+type Related<R> = Vec<(<R as DbrTable>::Id, Weak<Mutex<RecordMetadata<R>>>)>;
+
 /// Per DBR Instance record cache
 ///
 /// For example, `ops`/`c1` and `ops`/`c2` will have their own record caches.
@@ -18,8 +21,23 @@ pub struct DbrRecordCache {
 
 #[derive(Debug, Clone)]
 pub struct RecordMetadata<T> {
+    /// Version of the row as of when it was loaded, bumped by our own writes since, see `Table::version_field`
+    ///
+    /// 0 until the first write, the version is read from the record itself until then.
     pub update_time: u64,
     pub data: T,
+
+    /// Related records loaded ahead of time by a `prefetch`, keyed by the name of the relation accessor.
+    ///
+    /// Only weak handles are kept, records pointing at each other would never be dropped otherwise.
+    /// The handles the `prefetch` was run on keep the related records alive instead, see `Active::hold`.
+    pub relations: HashMap<&'static str, Arc<dyn Any + Send + Sync>>,
+
+    /// Fields changed by `stage` that haven't been saved yet.
+    pub dirty: BTreeSet<&'static str>,
+
+    /// The record as it was last loaded, only kept around while there are unsaved changes.
+    pub loaded: Option<T>,
 }
 
 impl<T> RecordMetadata<T> {
@@ -27,8 +45,119 @@ impl<T> RecordMetadata<T> {
         Self {
             update_time: 0,
             data: data,
+            relations: HashMap::new(),
+            dirty: BTreeSet::new(),
+            loaded: None,
         }
     }
+
+    /// Records prefetched under `name`, `None` if there weren't any or some of them have been dropped since.
+    pub fn relation<R: DbrTable>(&self, name: &str) -> Option<Vec<Active<R>>> {
+        let related = self.relations.get(name)?.downcast_ref::<Related<R>>()?;
+        related
+            .iter()
+            .map(|(id, data)| Some(Active::from_arc(id.clone(), data.upgrade()?)))
+            .collect()
+    }
+
+    pub fn set_relation<R: DbrTable>(&mut self, name: &'static str, related: &[Active<R>]) {
+        let related: Related<R> = related
+            .iter()
+            .map(|record| (record.id(), Arc::downgrade(record.data())))
+            .collect();
+        self.relations.insert(name, Arc::new(related));
+    }
+}
+
+impl<T: DbrTable> RecordMetadata<T> {
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Change the record locally without writing anything, the fields are marked dirty until saved.
+    pub fn stage(&mut self, partial: T::PartialModel) -> Result<(), DbrError> {
+        if self.loaded.is_none() {
+            self.loaded = Some(self.data.clone());
+        }
+
+        self.dirty.extend(partial.fields());
+        partial.apply(self)?;
+
+        // Foreign keys might have changed, so whatever was prefetched can't be trusted anymore.
+        self.relations.clear();
+        Ok(())
+    }
+
+    /// Current values of the dirty fields.
+    pub fn changes(&self) -> T::PartialModel {
+        let dirty: Vec<&str> = self.dirty.iter().copied().collect();
+        T::PartialModel::from_record(&self.data, &dirty)
+    }
+
+    /// Apply a partial that has been written to the database.
+    pub fn apply(&mut self, partial: T::PartialModel) -> Result<(), DbrError> {
+        self.saved(partial.clone())?;
+        partial.apply(self)?;
+
+        // Foreign keys might have changed, so whatever was prefetched can't be trusted anymore.
+        self.relations.clear();
+        Ok(())
+    }
+
+    /// The fields of `partial` have been written to the database, they aren't dirty anymore.
+    pub fn saved(&mut self, partial: T::PartialModel) -> Result<(), DbrError> {
+        for field in partial.fields() {
+            self.dirty.remove(field);
+        }
+
+        if self.dirty.is_empty() {
+            self.loaded = None;
+        } else if let Some(loaded) = &mut self.loaded {
+            partial.apply(&mut &mut *loaded)?;
+        }
+
+        Ok(())
+    }
+
+    /// Throw away any unsaved changes, going back to the record as it was last loaded.
+    pub fn discard(&mut self) {
+        if let Some(loaded) = self.loaded.take() {
+            self.data = loaded;
+            self.relations.clear();
+        }
+
+        self.dirty.clear();
+    }
+
+    /// Our own write bumped the version field `field` to `version`.
+    ///
+    /// The record as it was loaded gets the new version too, it's what the database has now.
+    pub fn written_at(&mut self, field: &str, version: u64) -> Result<(), DbrError> {
+        self.update_time = version;
+        if let Some(bumped) = T::PartialModel::version(field, version) {
+            if let Some(loaded) = &mut self.loaded {
+                bumped.clone().apply(&mut &mut *loaded)?;
+            }
+            bumped.apply(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the record with a freshly loaded copy, unsaved changes are kept on top of it.
+    pub fn reload(&mut self, record: T) -> Result<(), DbrError> {
+        let pending = self.changes();
+        let dirty = std::mem::take(&mut self.dirty);
+
+        *self = RecordMetadata::new(record);
+        if !dirty.is_empty() {
+            self.loaded = Some(self.data.clone());
+            self.dirty = dirty;
+            pending.apply(self)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<T> Deref for RecordMetadata<T> {
@@ -90,7 +219,7 @@ impl DbrRecordCache {
                                     {
                                         let mut locked_existing =
                                             strong.lock().map_err(|_| DbrError::PoisonError)?;
-                                        *locked_existing = RecordMetadata::new(record);
+                                        locked_existing.reload(record)?;
                                     }
 
                                     Ok(strong)
@@ -118,6 +247,58 @@ impl DbrRecordCache {
         }
     }
 
+    /// Register an already existing record, e.g. one loaded inside of a transaction that has since committed.
+    ///
+    /// If the record got cached some other way in the meantime then that one is updated instead.
+    pub fn insert_record<T: DbrTable + Any>(
+        &self,
+        id: <T as DbrTable>::Id,
+        record: &Arc<Mutex<RecordMetadata<T>>>,
+    ) -> Result<(), DbrError> {
+        if let Ok(existing) = self.record::<T>(id.clone()) {
+            if !Arc::ptr_eq(&existing, record) {
+                let data = record.lock().map_err(|_| DbrError::PoisonError)?.clone();
+                *existing.lock().map_err(|_| DbrError::PoisonError)? = data;
+            }
+
+            return Ok(());
+        }
+
+        let mut map = self.records.write().map_err(|_| DbrError::PoisonError)?;
+        match map.get_mut(&TypeId::of::<T>()) {
+            Some(records) => match records.downcast_mut::<Store<T>>() {
+                Some(downcasted) => {
+                    downcasted.insert(id, Arc::downgrade(record));
+                    Ok(())
+                }
+                None => Err(DbrError::DowncastError),
+            },
+            None => Err(DbrError::UnregisteredType),
+        }
+    }
+
+    /// Forget about a record, e.g. after it has been deleted.
+    ///
+    /// Anything still holding onto the record keeps its data, it just won't be handed out anymore.
+    pub fn remove_record<T: DbrTable + Any>(
+        &self,
+        id: <T as DbrTable>::Id,
+    ) -> Result<(), DbrError> {
+        self.assert_registered::<T>()?;
+
+        let mut map = self.records.write().map_err(|_| DbrError::PoisonError)?;
+        match map.get_mut(&TypeId::of::<T>()) {
+            Some(records) => match records.downcast_mut::<Store<T>>() {
+                Some(downcasted) => {
+                    downcasted.remove(&id);
+                    Ok(())
+                }
+                None => Err(DbrError::DowncastError),
+            },
+            None => Err(DbrError::UnregisteredType),
+        }
+    }
+
     pub fn record<T: DbrTable + Any>(
         &self,
         id: <T as DbrTable>::Id,
@@ -142,3 +323,53 @@ impl DbrRecordCache {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::tests::{song, PartialSong, Song};
+
+    fn song_record(version: i64) -> RecordMetadata<Song> {
+        RecordMetadata::new(song(version))
+    }
+
+    #[test]
+    fn writes_bump_the_version_field() {
+        let mut record = song_record(3);
+        record.written_at("version", 4).unwrap();
+
+        assert_eq!(record.update_time, 4);
+        assert_eq!(record.version, 4);
+        assert!(!record.is_dirty());
+    }
+
+    #[test]
+    fn writes_bump_the_loaded_version_under_staged_changes() {
+        let mut record = song_record(3);
+        record
+            .stage(PartialSong {
+                name: Some(Some("Yesterday".into())),
+                ..Default::default()
+            })
+            .unwrap();
+        record.written_at("version", 4).unwrap();
+
+        assert_eq!(record.version, 4);
+        assert_eq!(record.name.as_deref(), Some("Yesterday"));
+        assert!(record.is_dirty());
+
+        // Throwing the changes away goes back to what the database has, at the new version.
+        record.discard();
+        assert_eq!(record.version, 4);
+        assert_eq!(record.name.as_deref(), Some("Help"));
+    }
+
+    #[test]
+    fn writes_without_a_version_field_only_track_it() {
+        let mut record = song_record(3);
+        record.written_at("revision", 4).unwrap();
+
+        assert_eq!(record.update_time, 4);
+        assert_eq!(record.version, 3);
+    }
+}
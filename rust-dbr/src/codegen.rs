@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::prelude::*;
+
+/// Rust source for the `DbrTable` structs of the given schemas, every schema if empty.
+///
+/// Each schema becomes a module named after its handle, relations to tables in other
+/// schemas go through `super::<schema>`. Tables we can't derive for are left as comments.
+pub fn generate(metadata: &Metadata, schemas: &[String]) -> Result<String, DbrError> {
+    let mut selected: Vec<&Schema> = if schemas.is_empty() {
+        metadata.schemas.values().collect()
+    } else {
+        schemas
+            .iter()
+            .map(|name| metadata.lookup_schema(SchemaIdentifier::Name(name.clone())))
+            .collect::<Result<_, _>>()?
+    };
+    selected.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by `rust-dbr codegen`.").unwrap();
+    writeln!(out, "#![allow(dead_code)]").unwrap();
+
+    for schema in selected {
+        writeln!(out).unwrap();
+        writeln!(out, "pub mod {} {{", module_name(&schema.name)).unwrap();
+        writeln!(out, "    use rust_dbr::prelude::*;").unwrap();
+        writeln!(out, "    use rust_dbr_macros::DbrTable;").unwrap();
+
+        let tables: BTreeMap<&String, &TableId> = schema.tables.iter().collect();
+        for table_id in tables.values() {
+            let table = metadata.lookup_table(**table_id)?;
+            writeln!(out).unwrap();
+            table_source(&mut out, metadata, schema, table)?;
+        }
+
+        writeln!(out, "}}").unwrap();
+    }
+
+    Ok(out)
+}
+
+fn table_source(
+    out: &mut String,
+    metadata: &Metadata,
+    schema: &Schema,
+    table: &Table,
+) -> Result<(), DbrError> {
+    let struct_name = struct_name(&table.name);
+
+    // The derive needs the primary key to be called `id`.
+    let primary_key = table
+        .primary_key()
+        .map(|field_id| metadata.lookup_field(field_id))
+        .transpose()?;
+    match primary_key {
+        Some(field) if field.name == "id" => {}
+        Some(field) => {
+            writeln!(
+                out,
+                "    // Skipped {}.{}, its primary key is `{}` instead of `id`.",
+                schema.name, table.name, field.name
+            )
+            .unwrap();
+            return Ok(());
+        }
+        None => {
+            writeln!(
+                out,
+                "    // Skipped {}.{}, it has no primary key.",
+                schema.name, table.name
+            )
+            .unwrap();
+            return Ok(());
+        }
+    }
+
+    let mut fields: Vec<&Field> = table
+        .fields
+        .values()
+        .map(|field_id| metadata.lookup_field(*field_id))
+        .collect::<Result<_, _>>()?;
+    fields.sort_by_key(|field| field.id);
+
+    let mut relations: Vec<&Relation> = metadata.relations.values().collect();
+    relations.sort_by_key(|relation| relation.id);
+
+    // Relations pointing at us from other tables, the derive takes those on the struct.
+    let mut to_many = Vec::new();
+    for relation in &relations {
+        if relation.to_table_id == table.id {
+            let path = table_path(metadata, schema, relation.from_table_id)?;
+            if !to_many.contains(&path) {
+                to_many.push(path);
+            }
+        }
+    }
+
+    writeln!(out, "    #[derive(DbrTable, sqlx::FromRow, Debug, Clone)]").unwrap();
+    writeln!(out, "    #[table = \"{}.{}\"]", schema.name, table.name).unwrap();
+    for path in &to_many {
+        writeln!(out, "    #[relation({})]", path).unwrap();
+    }
+    writeln!(out, "    pub struct {} {{", struct_name).unwrap();
+
+    // Only one to-one relation per related table is supported by the derive.
+    let mut related = Vec::new();
+    for field in fields {
+        for relation in &relations {
+            if relation.from_field_id == field.id {
+                let path = table_path(metadata, schema, relation.to_table_id)?;
+                if !related.contains(&path) {
+                    writeln!(out, "        #[relation({})]", path).unwrap();
+                    related.push(path);
+                }
+            }
+        }
+
+        let ident = field_ident(&field.name);
+        if ident != field.name {
+            writeln!(out, "        #[sqlx(rename = \"{}\")]", field.name).unwrap();
+        }
+        writeln!(out, "        pub {}: {},", ident, rust_type(field)).unwrap();
+    }
+
+    writeln!(out, "    }}").unwrap();
+    Ok(())
+}
+
+/// Rust type for a field, the translator takes precedence over the data type.
+///
+/// Records are decoded through sqlx's `Any` driver which only has signed integers,
+/// so unsigned fields get the next signed type up. It has no decimals either, those go through
+/// our text backed `Decimal`.
+pub fn rust_type(field: &Field) -> String {
+    let base = match (field.translator(), field.kind()) {
+        (Some(Translator::Enum), _) => "Enum",
+        (Some(Translator::Dollars), _) => "Dollars",
+        (Some(Translator::UnixTime), _) => "UnixTime",
+        (Some(Translator::Percent), _) => "Percent",
+        (None, Some(kind)) => match kind {
+            // Unsigned bigints past i64::MAX won't decode, there's nothing bigger to go to.
+            DataType::BigInt => "i64",
+            DataType::Int if field.is_signed => "i32",
+            DataType::Int => "i64",
+            DataType::MediumInt => "i32",
+            DataType::SmallInt if field.is_signed => "i16",
+            DataType::SmallInt => "i32",
+            DataType::TinyInt => "i16",
+            DataType::Bool => "bool",
+            DataType::Float => "f32",
+            DataType::Double => "f64",
+            DataType::VarChar
+            | DataType::Char
+            | DataType::Text
+            | DataType::MediumText
+            | DataType::Enum => "String",
+            DataType::Decimal => "Decimal",
+            DataType::DateTime => "rust_dbr::chrono::NaiveDateTime",
+            DataType::Blob
+            | DataType::LongBlob
+            | DataType::MediumBlob
+            | DataType::TinyBlob
+            | DataType::Binary
+            | DataType::VarBinary => "Vec<u8>",
+        },
+        (None, None) => "String",
+    };
+
+    if field.is_nullable && !field.is_primary_key {
+        format!("Option<{}>", base)
+    } else {
+        base.to_owned()
+    }
+}
+
+fn table_path(metadata: &Metadata, schema: &Schema, table_id: TableId) -> Result<String, DbrError> {
+    let table = metadata.lookup_table(table_id)?;
+    if table.schema_id == schema.id {
+        Ok(struct_name(&table.name))
+    } else {
+        let other = metadata.lookup_schema(SchemaIdentifier::Id(table.schema_id))?;
+        Ok(format!(
+            "super::{}::{}",
+            module_name(&other.name),
+            struct_name(&table.name)
+        ))
+    }
+}
+
+/// `customer_order` -> `CustomerOrder`
+pub fn struct_name(table: &str) -> String {
+    table
+        .split(|c: char| c == '_' || c == '-' || c == ' ')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Struct field for a column, keywords are raw identifiers and renamed back by `#[sqlx(rename)]`.
+fn field_ident(column: &str) -> String {
+    match column {
+        // These can't be raw identifiers.
+        "crate" | "self" | "Self" | "super" => format!("{}_", column),
+        _ if is_keyword(column) => format!("r#{}", column),
+        _ => column.to_owned(),
+    }
+}
+
+fn module_name(schema: &str) -> String {
+    let name = schema
+        .to_lowercase()
+        .replace(|c: char| !c.is_alphanumeric(), "_");
+    if is_keyword(&name) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+fn is_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "as" | "async"
+            | "await"
+            | "break"
+            | "const"
+            | "continue"
+            | "crate"
+            | "dyn"
+            | "else"
+            | "enum"
+            | "extern"
+            | "false"
+            | "fn"
+            | "for"
+            | "if"
+            | "impl"
+            | "in"
+            | "let"
+            | "loop"
+            | "match"
+            | "mod"
+            | "move"
+            | "mut"
+            | "pub"
+            | "ref"
+            | "return"
+            | "self"
+            | "Self"
+            | "static"
+            | "struct"
+            | "super"
+            | "trait"
+            | "true"
+            | "type"
+            | "unsafe"
+            | "use"
+            | "where"
+            | "while"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song_field(name: &str) -> Field {
+        let metadata = Metadata::for_tests();
+        let field = metadata
+            .fields
+            .values()
+            .find(|field| field.name == name)
+            .unwrap();
+        field.clone()
+    }
+
+    fn with_type(mut field: Field, data_type: DataType, is_signed: bool) -> Field {
+        field.data_type = data_type.id();
+        field.is_signed = is_signed;
+        field
+    }
+
+    #[test]
+    fn integers_fit_their_width_and_sign() {
+        let plays = song_field("plays");
+        let cases = [
+            (DataType::BigInt, true, "i64"),
+            (DataType::Int, true, "i32"),
+            (DataType::Int, false, "i64"),
+            (DataType::SmallInt, true, "i16"),
+            (DataType::SmallInt, false, "i32"),
+            (DataType::TinyInt, false, "i16"),
+            (DataType::Bool, false, "bool"),
+        ];
+
+        for (data_type, is_signed, expected) in cases {
+            let field = with_type(plays.clone(), data_type, is_signed);
+            assert_eq!(rust_type(&field), expected, "{:?}", data_type);
+        }
+    }
+
+    #[test]
+    fn nullable_fields_are_options() {
+        assert_eq!(rust_type(&song_field("name")), "Option<String>");
+        assert_eq!(rust_type(&song_field("id")), "i64");
+    }
+
+    #[test]
+    fn datetimes_and_decimals_decode_into_their_own_types() {
+        let plays = song_field("plays");
+        assert_eq!(
+            rust_type(&with_type(plays.clone(), DataType::DateTime, false)),
+            "rust_dbr::chrono::NaiveDateTime"
+        );
+        assert_eq!(
+            rust_type(&with_type(plays.clone(), DataType::Decimal, true)),
+            "Decimal"
+        );
+
+        let mut released = with_type(song_field("name"), DataType::DateTime, false);
+        released.name = "released".into();
+        assert_eq!(
+            rust_type(&released),
+            "Option<rust_dbr::chrono::NaiveDateTime>"
+        );
+    }
+
+    #[test]
+    fn translators_win_over_the_data_type() {
+        let mut field = song_field("plays");
+        field.trans_id = Some(Translator::Dollars.id());
+        assert_eq!(rust_type(&field), "Dollars");
+
+        field.trans_id = Some(Translator::UnixTime.id());
+        field.is_nullable = true;
+        assert_eq!(rust_type(&field), "Option<UnixTime>");
+    }
+
+    #[test]
+    fn keyword_columns_are_raw_and_renamed() {
+        let mut metadata = Metadata::for_tests();
+        for field in metadata.fields.values_mut() {
+            match field.name.as_str() {
+                "name" => field.name = "type".into(),
+                "plays" => field.name = "self".into(),
+                _ => {}
+            }
+        }
+
+        let source = generate(&metadata, &[]).unwrap();
+        assert!(source.contains("#[sqlx(rename = \"type\")]\n        pub r#type: Option<String>,"));
+        assert!(source.contains("#[sqlx(rename = \"self\")]\n        pub self_: i16,"));
+        assert!(source.contains("        pub version: i64,"));
+        assert!(!source.contains("Skipped `"));
+    }
+}
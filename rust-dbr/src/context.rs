@@ -1,12 +1,23 @@
 use derive_more::Deref;
-use std::collections::{HashMap, VecDeque};
-use std::sync::Arc;
+use futures::stream::{BoxStream, StreamExt};
+use sqlx::any::{AnyQueryResult, AnyRow};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::Instrument;
 
 use crate::{
+    dialect::placeholder_count,
+    filter::BindValue,
     metadata::{FieldId, RelationId, TableId},
+    observer::{QueryObserver, QueryTimer},
     prelude::*,
+    transaction::Transaction,
 };
 
+/// How many rows `Context::fetch_stream_as` reads ahead of the consumer.
+const STREAM_BUFFER: usize = 64;
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RelationPath {
     pub base: TableId,
@@ -42,6 +53,15 @@ pub struct Context {
     pub client_id: Option<i64>,
     pub instances: DbrInstances,
     pub metadata: Metadata,
+
+    /// Set on contexts made with `begin_transaction`, every statement run through them goes through this.
+    pub transaction: Option<Arc<Transaction>>,
+
+    /// Told about every statement run through this context, see `with_observer`
+    pub observer: Option<Arc<dyn QueryObserver>>,
+
+    /// Setters only stage their changes on the record until `Active::save`, see `with_deferred_writes`
+    pub deferred_writes: bool,
 }
 
 #[derive(Deref, Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
@@ -51,6 +71,9 @@ pub struct QueryId(#[deref] u32);
 pub struct TableRegistry {
     instances: HashMap<Option<RelationId>, JoinedTableIndex>,
     relation_hash: HashMap<RelationChain, (Option<JoinedTableIndex>, JoinedTableIndex)>,
+
+    /// Chains referenced by a filter that isn't under an `Or`, rows without them can never match.
+    required: HashSet<RelationChain>,
 }
 
 impl TableRegistry {
@@ -58,21 +81,36 @@ impl TableRegistry {
         Self {
             instances: HashMap::new(),
             relation_hash: HashMap::new(),
+            required: HashSet::new(),
         }
     }
 
     /// We need this to be in the correct order.
     pub fn table_instances(
-        self,
+        &self,
     ) -> Vec<(RelationChain, (Option<JoinedTableIndex>, JoinedTableIndex))> {
-        self.relation_hash.into_iter().collect()
+        self.relation_hash
+            .iter()
+            .map(|(chain, indices)| (chain.clone(), *indices))
+            .collect()
+    }
+
+    /// Whether the chain is only referenced under an `Or`, those have to be `LEFT JOIN`ed
+    /// so the other side of the `Or` can still match when there is nothing to join.
+    pub fn is_optional(&self, chain: &RelationChain) -> bool {
+        !self.required.contains(chain)
     }
 
     pub fn add(
         &mut self,
         _context: &Context,
         chain: &RelationChain,
+        optional: bool,
     ) -> Result<(Option<JoinedTableIndex>, JoinedTableIndex), DbrError> {
+        if !optional {
+            self.required.insert(chain.clone());
+        }
+
         match self.relation_hash.get(&chain) {
             Some(index) => Ok(*index),
             None => {
@@ -99,6 +137,30 @@ impl TableRegistry {
 }
 
 impl Context {
+    pub fn new(client_id: Option<i64>, instances: DbrInstances, metadata: Metadata) -> Self {
+        Self {
+            client_id,
+            instances,
+            metadata,
+            transaction: None,
+            observer: None,
+            deferred_writes: false,
+        }
+    }
+
+    /// Report every statement run through this context (and any transactions begun from it) to `observer`
+    pub fn with_observer(mut self, observer: Arc<dyn QueryObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Have the generated setters (and `set`) change records locally instead of running an `UPDATE` each,
+    /// the changed fields get written together by `Active::save`.
+    pub fn with_deferred_writes(mut self) -> Self {
+        self.deferred_writes = true;
+        self
+    }
+
     pub fn client_id(&self) -> Option<i64> {
         self.client_id
     }
@@ -115,8 +177,354 @@ impl Context {
         self.instances.lookup_by_handle(handle, self.client_tag())
     }
 
+    /// Instance the records of `T` live on, the same one `Select` resolves its table to.
+    pub fn instance_for<T: DbrTable>(&self) -> Result<Arc<DbrInstance>, DbrError> {
+        let table = self.metadata.lookup_dbr_table::<T>()?;
+        self.instance_by_schema(table.schema_id)
+    }
+
+    /// Transactional copy of this context.
+    ///
+    /// Statements run through it are part of one transaction per instance until `commit` or `rollback`,
+    /// changes to the shared record cache are held back until the commit. Committing isn't atomic
+    /// across instances, see `Transaction`
     pub fn begin_transaction(&self) -> Context {
-        unimplemented!()
+        Context {
+            transaction: Some(Arc::new(Transaction::new())),
+            ..self.clone()
+        }
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    pub async fn commit(&self) -> Result<(), DbrError> {
+        match &self.transaction {
+            Some(transaction) => transaction.commit().await,
+            None => Err(DbrError::NotInTransaction),
+        }
+    }
+
+    pub async fn rollback(&self) -> Result<(), DbrError> {
+        match &self.transaction {
+            Some(transaction) => transaction.rollback().await,
+            None => Err(DbrError::NotInTransaction),
+        }
+    }
+
+    pub async fn execute(
+        &self,
+        instance: &DbrInstance,
+        sql: &str,
+        arguments: BindValue,
+    ) -> Result<AnyQueryResult, DbrError> {
+        let argument_count = placeholder_count(sql);
+        let sql = instance.dialect().finalize(sql);
+        let timer = QueryTimer::start(self.observer.as_ref(), instance, &sql, argument_count);
+
+        let query = sqlx::query_with(&sql, arguments);
+        let result = async {
+            Ok::<_, DbrError>(match &self.transaction {
+                Some(transaction) => {
+                    let mut connection = transaction.lock(instance).await?;
+                    query.execute(&mut *connection).await?
+                }
+                None => query.execute(&instance.pool).await?,
+            })
+        }
+        .instrument(timer.span())
+        .await;
+
+        timer.finish(&result, |result| result.rows_affected());
+        result
+    }
+
+    pub async fn fetch_all(
+        &self,
+        instance: &DbrInstance,
+        sql: &str,
+        arguments: BindValue,
+    ) -> Result<Vec<AnyRow>, DbrError> {
+        let argument_count = placeholder_count(sql);
+        let sql = instance.dialect().finalize(sql);
+        let timer = QueryTimer::start(self.observer.as_ref(), instance, &sql, argument_count);
+
+        let query = sqlx::query_with(&sql, arguments);
+        let result = async {
+            Ok::<_, DbrError>(match &self.transaction {
+                Some(transaction) => {
+                    let mut connection = transaction.lock(instance).await?;
+                    query.fetch_all(&mut *connection).await?
+                }
+                None => query.fetch_all(&instance.pool).await?,
+            })
+        }
+        .instrument(timer.span())
+        .await;
+
+        timer.finish(&result, |rows| rows.len() as u64);
+        result
+    }
+
+    pub async fn fetch_all_as<T>(
+        &self,
+        instance: &DbrInstance,
+        sql: &str,
+        arguments: BindValue,
+    ) -> Result<Vec<T>, DbrError>
+    where
+        T: for<'r> sqlx::FromRow<'r, AnyRow> + Send + Unpin,
+    {
+        let argument_count = placeholder_count(sql);
+        let sql = instance.dialect().finalize(sql);
+        let timer = QueryTimer::start(self.observer.as_ref(), instance, &sql, argument_count);
+
+        let query = sqlx::query_as_with(&sql, arguments);
+        let result = async {
+            Ok::<_, DbrError>(match &self.transaction {
+                Some(transaction) => {
+                    let mut connection = transaction.lock(instance).await?;
+                    query.fetch_all(&mut *connection).await?
+                }
+                None => query.fetch_all(&instance.pool).await?,
+            })
+        }
+        .instrument(timer.span())
+        .await;
+
+        timer.finish(&result, |records| records.len() as u64);
+        result
+    }
+
+    /// Stream rows as they are read rather than waiting on all of them.
+    ///
+    /// Rows are read on a separate task, at most `STREAM_BUFFER` ahead of whoever is consuming the stream.
+    /// Inside of a transaction the connection can't be shared with the rest of the transaction while the
+    /// stream is alive, so the rows are all fetched up front there.
+    ///
+    /// The observer hears about the stream once it has been read to the end.
+    pub fn fetch_stream_as<T>(
+        &self,
+        instance: Arc<DbrInstance>,
+        sql: String,
+        arguments: BindValue,
+    ) -> BoxStream<'static, Result<T, DbrError>>
+    where
+        T: for<'r> sqlx::FromRow<'r, AnyRow> + Send + Unpin + 'static,
+    {
+        if self.in_transaction() {
+            let context = self.clone();
+            return futures::stream::once(async move {
+                context.fetch_all_as::<T>(&instance, &sql, arguments).await
+            })
+            .map(|records| match records {
+                Ok(records) => futures::stream::iter(records.into_iter().map(Ok)).boxed(),
+                Err(err) => futures::stream::once(async { Err(err) }).boxed(),
+            })
+            .flatten()
+            .boxed();
+        }
+
+        let observer = self.observer.clone();
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            let argument_count = placeholder_count(&sql);
+            let sql = instance.dialect().finalize(&sql);
+            let timer = QueryTimer::start(observer.as_ref(), &instance, &sql, argument_count);
+
+            let mut rows = sqlx::query_as_with::<_, T, _>(&sql, arguments).fetch(&instance.pool);
+            let mut row_count = 0;
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(record) => {
+                        row_count += 1;
+
+                        // Nobody is listening anymore, no point in reading the rest.
+                        if sender.send(Ok(record)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        let failed = Err(DbrError::from(err));
+                        timer.finish(&failed, |_: &()| 0);
+                        if let Err(err) = failed {
+                            let _ = sender.send(Err(err)).await;
+                        }
+                        return;
+                    }
+                }
+            }
+
+            timer.finish(&Ok(()), |_| row_count);
+        });
+
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|row| (row, receiver))
+        })
+        .boxed()
+    }
+
+    /// Make a change to the shared record caches, inside of a transaction this waits for the commit.
+    pub fn cache_write<F>(&self, write: F) -> Result<(), DbrError>
+    where
+        F: FnOnce() -> Result<(), DbrError> + Send + 'static,
+    {
+        match &self.transaction {
+            Some(transaction) => transaction.defer(write),
+            None => write(),
+        }
+    }
+
+    /// Change a record, inside of a transaction only the transaction sees the change until it commits.
+    ///
+    /// There the transaction's own copy of the record changes right away (one is made if there isn't one yet)
+    /// and a record handed out from outside of the transaction changes on commit.
+    pub fn record_write<T, F>(&self, record: &Active<T>, write: F) -> Result<(), DbrError>
+    where
+        T: DbrTable,
+        F: Fn(&mut RecordMetadata<T>) -> Result<(), DbrError> + Send + 'static,
+    {
+        if !self.in_transaction() {
+            let mut data = record.data().lock().map_err(|_| DbrError::PoisonError)?;
+            return write(&mut data);
+        }
+
+        let local = self.transaction_record(record)?;
+        {
+            let mut data = local.lock().map_err(|_| DbrError::PoisonError)?;
+            write(&mut data)?;
+        }
+
+        if Arc::ptr_eq(&local, record.data()) {
+            return Ok(());
+        }
+
+        let record = record.clone();
+        self.cache_write(move || {
+            let mut data = record.data().lock().map_err(|_| DbrError::PoisonError)?;
+            write(&mut data)
+        })
+    }
+
+    /// The record as this context sees it, inside of a transaction that is the transaction's own copy.
+    pub fn current_record<T: DbrTable>(
+        &self,
+        record: &Active<T>,
+    ) -> Result<Arc<Mutex<RecordMetadata<T>>>, DbrError> {
+        if !self.in_transaction() {
+            return Ok(record.data().clone());
+        }
+
+        self.transaction_record(record)
+    }
+
+    /// A record still alive in the record cache, inside of a transaction its copy of the record if it has one.
+    pub fn cached_record<T: DbrTable>(
+        &self,
+        instance: &DbrInstance,
+        id: T::Id,
+    ) -> Result<Option<Active<T>>, DbrError> {
+        if let Some(transaction) = &self.transaction {
+            match transaction.cache(instance)?.record::<T>(id.clone()) {
+                Ok(local) => return Ok(Some(Active::from_arc(id, local))),
+                Err(DbrError::RecordNotFetched) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        match instance.cache.record::<T>(id.clone()) {
+            Ok(record_ref) => Ok(Some(Active::from_arc(id, record_ref))),
+            Err(DbrError::RecordNotFetched) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The transaction's copy of a record, copied from `record` if the transaction doesn't have one yet.
+    fn transaction_record<T: DbrTable>(
+        &self,
+        record: &Active<T>,
+    ) -> Result<Arc<Mutex<RecordMetadata<T>>>, DbrError> {
+        let transaction = self
+            .transaction
+            .as_ref()
+            .ok_or(DbrError::NotInTransaction)?;
+        let instance = self.instance_for::<T>()?;
+        let cache = transaction.cache(&instance)?;
+        match cache.record::<T>(record.id()) {
+            Ok(local) => Ok(local),
+            Err(DbrError::RecordNotFetched) => {
+                let data = record
+                    .data()
+                    .lock()
+                    .map_err(|_| DbrError::PoisonError)?
+                    .clone();
+                let local = Arc::new(Mutex::new(data));
+                cache.insert_record(record.id(), &local)?;
+                transaction.hold(local.clone())?;
+                Ok(local)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn apply_partial<T: DbrTable>(
+        &self,
+        record: &Active<T>,
+        partial: T::PartialModel,
+    ) -> Result<(), DbrError> {
+        if let Some(_id) = partial.id() {
+            return Err(DbrError::CannotSetID);
+        }
+
+        self.record_write(record, move |data| data.apply(partial.clone()))
+    }
+
+    /// Drop a record from the record cache, e.g. after it has been deleted.
+    pub fn remove_record<T: DbrTable>(
+        &self,
+        instance: &Arc<DbrInstance>,
+        id: T::Id,
+    ) -> Result<(), DbrError> {
+        if let Some(transaction) = &self.transaction {
+            transaction
+                .cache(instance)?
+                .remove_record::<T>(id.clone())?;
+        }
+
+        let instance = instance.clone();
+        self.cache_write(move || instance.cache.remove_record::<T>(id))
+    }
+
+    /// Hand out a freshly loaded record from the cache of the instance it came from.
+    ///
+    /// Inside of a transaction the record goes into the transaction's own cache instead,
+    /// the shared cache is updated from it once the transaction commits.
+    pub fn register_record<T: DbrTable>(
+        &self,
+        instance: &Arc<DbrInstance>,
+        record: T,
+    ) -> Result<Active<T>, DbrError> {
+        let id = record.id();
+        let transaction = match &self.transaction {
+            Some(transaction) => transaction,
+            None => {
+                let record_ref = instance.cache.set_record(id.clone(), record)?;
+                return Ok(Active::from_arc(id, record_ref));
+            }
+        };
+
+        let record_ref = transaction
+            .cache(instance)?
+            .set_record(id.clone(), record)?;
+        transaction.hold(record_ref.clone())?;
+
+        let instance = instance.clone();
+        let cached_id = id.clone();
+        let pending_ref = record_ref.clone();
+        self.cache_write(move || instance.cache.insert_record(cached_id, &pending_ref))?;
+
+        Ok(Active::from_arc(id, record_ref))
     }
 
     /// I'm taking the liberty of just calling a string of relations like
@@ -131,6 +539,18 @@ impl Context {
     }
 }
 
+#[cfg(test)]
+impl Context {
+    /// Context over `Metadata::for_tests` with an `ops` instance that never connects.
+    ///
+    /// Has to be made inside of a runtime.
+    pub(crate) fn for_tests() -> Self {
+        let mut instances = DbrInstances::new();
+        instances.insert(DbrInstance::for_tests("Mysql"));
+        Self::new(None, instances, Metadata::for_tests())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RelationChain {
     base: TableId,
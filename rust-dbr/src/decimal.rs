@@ -0,0 +1,101 @@
+use std::fmt;
+use std::str::FromStr;
+
+use sqlx::any::{Any, AnyTypeInfo, AnyValueRef};
+use sqlx::database::HasArguments;
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, Type, TypeInfo};
+
+use crate::prelude::*;
+
+/// Value of a `decimal` field, kept as the exact text of the number.
+///
+/// sqlx's `Any` driver has no decimal type we can decode into, the number is read as text instead
+/// which MySQL and SQLite hand out for their decimals. Postgres only sends `numeric` as text
+/// when it is cast, e.g. `price::text`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Decimal(String);
+
+impl Decimal {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Lossy, for when exactness doesn't matter.
+    pub fn to_f64(&self) -> f64 {
+        self.0.parse().unwrap_or(f64::NAN)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = DbrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let digits = text.strip_prefix('-').unwrap_or(text);
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let valid = !whole.is_empty()
+            && whole.chars().all(|c| c.is_ascii_digit())
+            && fraction.chars().all(|c| c.is_ascii_digit());
+
+        if valid {
+            Ok(Self(text.to_owned()))
+        } else {
+            Err(DbrError::InvalidDecimal(text.to_owned()))
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<Decimal> for String {
+    fn from(decimal: Decimal) -> Self {
+        decimal.0
+    }
+}
+
+impl Type<Any> for Decimal {
+    fn type_info() -> AnyTypeInfo {
+        <String as Type<Any>>::type_info()
+    }
+
+    fn compatible(ty: &AnyTypeInfo) -> bool {
+        <String as Type<Any>>::compatible(ty)
+            || matches!(
+                ty.name().to_uppercase().as_str(),
+                "DECIMAL" | "NUMERIC" | "REAL" | "INTEGER"
+            )
+    }
+}
+
+impl<'q> Encode<'q, Any> for Decimal {
+    fn encode_by_ref(&self, buf: &mut <Any as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<'q, Any>>::encode(self.0.clone(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Any> for Decimal {
+    fn decode(value: AnyValueRef<'r>) -> Result<Self, BoxDynError> {
+        let text = <String as Decode<'r, Any>>::decode(value)?;
+        Ok(text.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_decimals_only() {
+        for valid in ["0", "-12", "12.50", "12."] {
+            assert_eq!(valid.parse::<Decimal>().unwrap().as_str(), valid);
+        }
+        for invalid in ["", "-", ".5", "1e5", "12,50", "NaN"] {
+            assert!(invalid.parse::<Decimal>().is_err(), "{}", invalid);
+        }
+    }
+}
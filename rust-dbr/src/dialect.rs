@@ -0,0 +1,233 @@
+use crate::prelude::*;
+
+/// Differences in SQL between the backends we support.
+///
+/// Statements are built with `?` placeholders and identifiers quoted through here,
+/// `finalize` rewrites the placeholders right before a statement is run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Dialect {
+    MySql,
+    Postgres,
+    Sqlite,
+}
+
+impl From<InstanceModule> for Dialect {
+    fn from(module: InstanceModule) -> Self {
+        match module {
+            InstanceModule::MySql => Self::MySql,
+            InstanceModule::Postgres => Self::Postgres,
+            InstanceModule::SQLite => Self::Sqlite,
+        }
+    }
+}
+
+impl Dialect {
+    fn quote_char(&self) -> char {
+        match self {
+            Self::MySql => '`',
+            Self::Postgres | Self::Sqlite => '"',
+        }
+    }
+
+    /// Quote a table, field or database name, e.g. `order` -> `` `order` `` for MySQL or `"order"` otherwise.
+    pub fn quote_identifier(&self, identifier: &str) -> String {
+        let quote = self.quote_char();
+        let mut quoted = String::with_capacity(identifier.len() + 2);
+        quoted.push(quote);
+        for c in identifier.chars() {
+            // Doubling up the quote is how all of them escape it.
+            if c == quote {
+                quoted.push(quote);
+            }
+            quoted.push(c);
+        }
+        quoted.push(quote);
+        quoted
+    }
+
+    /// `table.column`, both quoted.
+    pub fn column(&self, table: &str, column: &str) -> String {
+        format!(
+            "{}.{}",
+            self.quote_identifier(table),
+            self.quote_identifier(column)
+        )
+    }
+
+    /// Whether a connection can reach into other databases on the same server.
+    ///
+    /// Only MySQL can, a Postgres or SQLite connection is bound to the one database it was opened on.
+    pub fn crosses_databases(&self) -> bool {
+        match self {
+            Self::MySql => true,
+            Self::Postgres | Self::Sqlite => false,
+        }
+    }
+
+    /// Table qualified by the database it lives in.
+    ///
+    /// Postgres and SQLite already point at the right database so the table name is left alone there,
+    /// see `crosses_databases`.
+    pub fn qualified_table(&self, database: &str, table: &str) -> String {
+        if self.crosses_databases() {
+            self.column(database, table)
+        } else {
+            self.quote_identifier(table)
+        }
+    }
+
+    /// `DELETE` of `count` rows of a table by their key, e.g. `DELETE FROM \`ops\`.\`song\` WHERE \`id\` IN (?, ?)`
+    pub fn delete_by_keys(&self, database: &str, table: &str, key: &str, count: usize) -> String {
+        format!(
+            "DELETE FROM {} WHERE {} IN ({})",
+            self.qualified_table(database, table),
+            self.quote_identifier(key),
+            vec!["?"; count].join(", ")
+        )
+    }
+
+    /// Whether inserts need `RETURNING` to get the new id back, the driver doesn't report it otherwise.
+    pub fn needs_returning(&self) -> bool {
+        match self {
+            Self::Postgres => true,
+            Self::MySql | Self::Sqlite => false,
+        }
+    }
+
+    /// First id of a multi-row insert, going by the id the driver reported for it.
+    ///
+    /// MySQL reports the first id of the statement, SQLite the last one.
+    pub fn first_inserted_id(&self, reported: i64, rows: usize) -> i64 {
+        match self {
+            Self::Sqlite => reported - (rows as i64 - 1),
+            Self::MySql | Self::Postgres => reported,
+        }
+    }
+
+    /// What to put in `LIMIT` when there is only an `OFFSET`, MySQL and SQLite can't have one without the other.
+    pub fn unbounded_limit(&self) -> Option<&'static str> {
+        match self {
+            Self::MySql => Some("18446744073709551615"),
+            Self::Sqlite => Some("-1"),
+            Self::Postgres => None,
+        }
+    }
+
+    /// Put NULLs before everything else, which is what MySQL and SQLite do already, so the order
+    /// of a select is the same on every backend. Keyset pagination depends on it.
+    pub fn nulls_first(&self, descending: bool) -> &'static str {
+        match (self, descending) {
+            (Self::Postgres, false) => " NULLS FIRST",
+            (Self::Postgres, true) => " NULLS LAST",
+            (Self::MySql | Self::Sqlite, _) => "",
+        }
+    }
+
+    /// Rewrite `?` placeholders into what the backend expects, `$1`, `$2`, ... for Postgres.
+    ///
+    /// Anything inside of quotes is left alone.
+    pub fn finalize(&self, sql: &str) -> String {
+        match self {
+            Self::MySql | Self::Sqlite => sql.to_owned(),
+            Self::Postgres => map_placeholders(sql, |placeholder| format!("${}", placeholder)),
+        }
+    }
+}
+
+/// How many `?` placeholders a statement has, before it is finalized.
+pub fn placeholder_count(sql: &str) -> usize {
+    let mut count = 0;
+    map_placeholders(sql, |placeholder| {
+        count = placeholder;
+        String::new()
+    });
+    count
+}
+
+/// Replace every `?` outside of quotes with `replace(n)`, counting from 1.
+fn map_placeholders<F>(sql: &str, mut replace: F) -> String
+where
+    F: FnMut(usize) -> String,
+{
+    let mut mapped = String::with_capacity(sql.len());
+    let mut quoted_by = None;
+    let mut placeholder = 0;
+    for c in sql.chars() {
+        match (quoted_by, c) {
+            (None, '?') => {
+                placeholder += 1;
+                mapped.push_str(&replace(placeholder));
+                continue;
+            }
+            (None, '\'' | '"' | '`') => quoted_by = Some(c),
+            (Some(quote), c) if quote == c => quoted_by = None,
+            _ => {}
+        }
+
+        mapped.push(c);
+    }
+
+    mapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_identifiers_per_backend() {
+        assert_eq!(Dialect::MySql.quote_identifier("order"), "`order`");
+        assert_eq!(Dialect::Postgres.quote_identifier("order"), "\"order\"");
+        assert_eq!(Dialect::Sqlite.quote_identifier("we\"ird"), "\"we\"\"ird\"");
+        assert_eq!(Dialect::MySql.quote_identifier("we`ird"), "`we``ird`");
+    }
+
+    #[test]
+    fn qualifies_tables_only_across_databases() {
+        assert_eq!(
+            Dialect::MySql.qualified_table("ops", "song"),
+            "`ops`.`song`"
+        );
+        assert_eq!(Dialect::Postgres.qualified_table("ops", "song"), "\"song\"");
+        assert_eq!(Dialect::Sqlite.qualified_table("ops", "song"), "\"song\"");
+    }
+
+    #[test]
+    fn deletes_by_keys_from_the_qualified_table() {
+        assert_eq!(
+            Dialect::MySql.delete_by_keys("ops", "song", "id", 3),
+            "DELETE FROM `ops`.`song` WHERE `id` IN (?, ?, ?)"
+        );
+        assert_eq!(
+            Dialect::Postgres.finalize(&Dialect::Postgres.delete_by_keys("ops", "song", "id", 2)),
+            "DELETE FROM \"song\" WHERE \"id\" IN ($1, $2)"
+        );
+    }
+
+    #[test]
+    fn rewrites_placeholders_for_postgres() {
+        let sql = "SELECT * FROM \"song\" WHERE \"id\" = ? AND \"name\" IN (?, ?)";
+        assert_eq!(
+            Dialect::Postgres.finalize(sql),
+            "SELECT * FROM \"song\" WHERE \"id\" = $1 AND \"name\" IN ($2, $3)"
+        );
+        assert_eq!(Dialect::MySql.finalize(sql), sql);
+        assert_eq!(Dialect::Sqlite.finalize(sql), sql);
+    }
+
+    #[test]
+    fn leaves_quoted_question_marks_alone() {
+        let sql = "SELECT '?', \"what?\" FROM `a?` WHERE x = ?";
+        assert_eq!(
+            Dialect::Postgres.finalize(sql),
+            "SELECT '?', \"what?\" FROM `a?` WHERE x = $1"
+        );
+        assert_eq!(placeholder_count(sql), 1);
+    }
+
+    #[test]
+    fn counts_placeholders() {
+        assert_eq!(placeholder_count("SELECT 1"), 0);
+        assert_eq!(placeholder_count("INSERT INTO t VALUES (?, ?), (?, ?)"), 4);
+    }
+}
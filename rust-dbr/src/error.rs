@@ -18,6 +18,37 @@ pub enum DbrError {
     },
     MetadataError(crate::metadata::MetadataError),
     UnfinishedExternalSubquery,
+    MissingFields {
+        table: String,
+        fields: Vec<String>,
+    },
+    InvalidInsertId(i64),
+    NotInTransaction,
+    UnsupportedModule(String),
+    MissingDatabaseFile(DbrInstanceId),
+    InvalidCursor(String),
+    RecordNotFound {
+        table: String,
+    },
+    MultipleRecords {
+        table: String,
+    },
+    Io(std::io::Error),
+    SerdeJson(serde_json::Error),
+    InvalidTranslation {
+        translator: crate::translator::Translator,
+        value: String,
+    },
+    InvalidDecimal(String),
+    Validation {
+        table: String,
+        failures: Vec<crate::validation::ValidationFailure>,
+    },
+    /// The row was changed by someone else since it was loaded, `current` is the fresh `Active<T>`, see `conflicting`
+    Conflict {
+        table: String,
+        current: Box<dyn std::any::Any + Send + Sync>,
+    },
 }
 
 impl std::fmt::Display for DbrError {
@@ -59,6 +90,56 @@ impl std::fmt::Display for DbrError {
                 f,
                 "contains unfinished external subquery, this must be run before the parent"
             ),
+            Self::MissingFields { table, fields } => write!(
+                f,
+                "missing required fields for '{}': {}",
+                table,
+                fields.join(", ")
+            ),
+            Self::InvalidInsertId(id) => {
+                write!(f, "inserted id {} doesn't fit in the id of the table", id)
+            }
+            Self::NotInTransaction => write!(f, "context is not in a transaction"),
+            Self::UnsupportedModule(module) => write!(f, "unsupported instance module: {}", module),
+            Self::MissingDatabaseFile(id) => {
+                write!(f, "sqlite instance {:?} is missing a dbfile", id)
+            }
+            Self::InvalidCursor(cursor) => write!(f, "invalid page cursor: {}", cursor),
+            Self::RecordNotFound { table } => write!(f, "no {} record matched", table),
+            Self::MultipleRecords { table } => {
+                write!(f, "expected at most one {} record, more matched", table)
+            }
+            Self::Io(err) => write!(f, "io error: {}", err),
+            Self::SerdeJson(err) => write!(f, "json error: {}", err),
+            Self::InvalidTranslation { translator, value } => {
+                write!(f, "{} is not a valid {:?} value", value, translator)
+            }
+            Self::InvalidDecimal(text) => write!(f, "{} is not a decimal number", text),
+            Self::Validation { table, failures } => write!(
+                f,
+                "invalid values for '{}': {}",
+                table,
+                failures
+                    .iter()
+                    .map(|failure| failure.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Conflict { table, .. } => write!(
+                f,
+                "'{}' record was changed by someone else since it was loaded",
+                table
+            ),
+        }
+    }
+}
+
+impl DbrError {
+    /// The fresh record of a `Conflict`, already updated in the record cache.
+    pub fn conflicting<T: crate::prelude::DbrTable>(&self) -> Option<&crate::prelude::Active<T>> {
+        match self {
+            Self::Conflict { current, .. } => current.downcast_ref(),
+            _ => None,
         }
     }
 }
@@ -71,6 +152,18 @@ impl From<sqlx::Error> for DbrError {
     }
 }
 
+impl From<std::io::Error> for DbrError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for DbrError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::SerdeJson(err)
+    }
+}
+
 impl From<crate::metadata::MetadataError> for DbrError {
     fn from(err: crate::metadata::MetadataError) -> Self {
         Self::MetadataError(err)
@@ -1,12 +1,22 @@
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
 use derive_more::Deref;
-use sqlx::mysql::MySqlArguments;
+use futures::{
+    future::BoxFuture,
+    stream::{BoxStream, StreamExt},
+};
+use sqlx::{
+    any::{AnyArguments, AnyRow},
+    Row,
+};
 
 //use crate::{metadata::{TableId, FieldId}, RelationPath, Context};
 use crate::prelude::*;
 
-type BindValue = MySqlArguments;
+pub type BindValue = AnyArguments<'static>;
+
+/// Most keys deleted by one statement, see `ResolvedSelect::delete`
+const DELETE_CHUNK: usize = 1000;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OrderDirection {
@@ -23,11 +33,16 @@ pub struct Select {
     pub filters: Option<FilterTree>,
     pub order: Vec<(String, Option<OrderDirection>)>,
     pub limit: Option<BindValue>,
+    pub offset: Option<BindValue>,
+    pub after: Option<Vec<CursorValue>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ResolvedJoin {
     pub length: usize,
+
+    /// `LEFT JOIN` rather than `JOIN`, see `TableRegistry::is_optional`
+    pub optional: bool,
     pub from_table: ResolvedTable,
     pub from_field: Field,
     pub from_instance_index: Option<JoinedTableIndex>,
@@ -39,12 +54,12 @@ pub struct ResolvedJoin {
 impl ResolvedJoin {
     pub fn as_sql(&self) -> String {
         format!(
-            "JOIN {} ON ({}.{} = {}.{})",
+            "{} {} ON ({} = {})",
+            if self.optional { "LEFT JOIN" } else { "JOIN" },
             self.to_table.instanced_with_schema(self.to_instance_index),
-            self.from_table.instanced(self.from_instance_index),
-            self.from_field.name,
-            self.to_table.instanced(self.to_instance_index),
-            self.to_field.name,
+            self.from_table
+                .column(self.from_instance_index, &self.from_field),
+            self.to_table.column(self.to_instance_index, &self.to_field),
         )
     }
 }
@@ -91,13 +106,23 @@ impl ResolvedTable {
         }
     }
     pub fn instanced_with_schema(&self, instance: Option<JoinedTableIndex>) -> String {
+        let dialect = self.dialect();
         format!(
-            "{}.{} AS {}",
-            self.instance.info.database_name(),
-            self.table.name,
-            self.instanced(instance),
+            "{} AS {}",
+            dialect.qualified_table(self.instance.info.database_name(), &self.table.name),
+            dialect.quote_identifier(&self.instanced(instance)),
         )
     }
+
+    /// Quoted column of this table under the alias for `instance`.
+    pub fn column(&self, instance: Option<JoinedTableIndex>, field: &Field) -> String {
+        self.dialect()
+            .column(&self.instanced(instance), &field.name)
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.instance.dialect()
+    }
 }
 
 impl PartialEq for ResolvedTable {
@@ -112,10 +137,18 @@ impl PartialEq for ResolvedTable {
 pub struct ResolvedSelect {
     pub fields: Vec<Field>,
     pub primary_table: ResolvedTable,
+    pub primary_key: Option<Field>,
     pub joins: Vec<ResolvedJoin>,
+
+    /// Set when joining through a to-many relation, otherwise the base rows would come back once per related row.
+    pub distinct: bool,
     pub filters: Option<ResolvedFilterTree>,
     pub order: Vec<(Field, Option<OrderDirection>)>,
     pub limit: Option<BindValue>,
+    pub offset: Option<BindValue>,
+
+    /// Order key values of the record to start after, see `Select::fetch_page`
+    pub after: Option<Vec<CursorValue>>,
 }
 
 impl Select {
@@ -127,9 +160,130 @@ impl Select {
             filters: None,
             order: Vec::new(),
             limit: None,
+            offset: None,
+            after: None,
         }
     }
 
+    /// Select every field of a table, filtered on one of its own fields.
+    ///
+    /// e.g. looking records up by their primary key.
+    pub fn filtered_on(
+        context: &Context,
+        table_id: TableId,
+        field: String,
+        op: FilterOp,
+        value: FilterValue,
+    ) -> Result<Self, DbrError> {
+        let table = context.metadata.lookup_table(table_id)?;
+        let mut select = Select::new(table_id);
+        select.fields = table.fields.values().cloned().collect();
+        select.filters = Some(FilterTree::Predicate(FilterPredicate {
+            path: RelationPath {
+                base: table_id,
+                relations: VecDeque::new(),
+                field,
+            },
+            op,
+            value,
+        }));
+
+        Ok(select)
+    }
+
+    /// Resolve and run the select, registering every record in the cache of the instance it came from.
+    pub async fn fetch_active<T: DbrTable>(
+        self,
+        context: &Context,
+    ) -> Result<Vec<Active<T>>, DbrError> {
+        let (instance, records) = self.fetch_records::<T>(context).await?;
+
+        let mut active_records = Vec::new();
+        for record in records {
+            active_records.push(context.register_record(&instance, record)?);
+        }
+
+        Ok(active_records)
+    }
+
+    /// Resolve and run the select without touching the record cache.
+    pub(crate) async fn fetch_records<T: DbrTable>(
+        self,
+        context: &Context,
+    ) -> Result<(Arc<DbrInstance>, Vec<T>), DbrError> {
+        let resolved_select = self
+            .resolve(context)?
+            .run_external_subqueries(context)
+            .await?;
+
+        let instance = resolved_select.primary_table.instance.clone();
+        if resolved_select.is_unsatisfiable() {
+            return Ok((instance, Vec::new()));
+        }
+
+        let (sql, args) = resolved_select.as_sql()?;
+        let records = context.fetch_all_as(&instance, &sql, args).await?;
+        Ok((instance, records))
+    }
+
+    /// Fetch the records as a stream, each one is registered in the record cache as it comes in.
+    ///
+    /// See `Context::fetch_stream_as`
+    pub async fn fetch_stream<T: DbrTable>(
+        self,
+        context: &Context,
+    ) -> Result<BoxStream<'static, Result<Active<T>, DbrError>>, DbrError> {
+        let resolved_select = self
+            .resolve(context)?
+            .run_external_subqueries(context)
+            .await?;
+        if resolved_select.is_unsatisfiable() {
+            return Ok(futures::stream::empty().boxed());
+        }
+
+        let instance = resolved_select.primary_table.instance.clone();
+        let (sql, args) = resolved_select.as_sql()?;
+        let records = context.fetch_stream_as::<T>(instance.clone(), sql, args);
+
+        let context = context.clone();
+        Ok(records
+            .map(move |record| record.and_then(|record| context.register_record(&instance, record)))
+            .boxed())
+    }
+
+    /// Count the records this select matches without fetching them.
+    pub async fn count(self, context: &Context) -> Result<i64, DbrError> {
+        let resolved_select = self
+            .resolve(context)?
+            .run_external_subqueries(context)
+            .await?;
+        if resolved_select.is_unsatisfiable() {
+            return Ok(0);
+        }
+
+        let instance = resolved_select.primary_table.instance.clone();
+        let (sql, args) = resolved_select.as_count_sql()?;
+        let rows = context.fetch_all(&instance, &sql, args).await?;
+        let row = rows.first().ok_or(DbrError::RecordNotFetched)?;
+        Ok(row.try_get::<i64, _>(0)?)
+    }
+
+    /// Whether this select matches anything, without fetching any records.
+    pub async fn exists(self, context: &Context) -> Result<bool, DbrError> {
+        let resolved_select = self
+            .resolve(context)?
+            .run_external_subqueries(context)
+            .await?;
+        if resolved_select.is_unsatisfiable() {
+            return Ok(false);
+        }
+
+        let instance = resolved_select.primary_table.instance.clone();
+        let (sql, args) = resolved_select.as_exists_sql()?;
+        let rows = context.fetch_all(&instance, &sql, args).await?;
+        Ok(rows.len() > 0)
+    }
+
     pub fn can_be_subquery(&self) -> bool {
         self.fields.len() == 1
     }
@@ -142,6 +296,8 @@ impl Select {
             filters,
             order,
             limit,
+            offset,
+            after,
         } = self;
 
         let mut joins = Vec::new();
@@ -160,6 +316,7 @@ impl Select {
             None => None,
         };
 
+        let mut distinct = false;
         let table_instances = table_registry.table_instances();
         for (chain, (from_instance_index, to_instance_index)) in table_instances {
             if let Some(relation_id) = chain.last_relation() {
@@ -169,8 +326,14 @@ impl Select {
                 let to_table = context.metadata.lookup_table(relation.to_table_id)?;
                 let to_field = context.metadata.lookup_field(relation.to_field_id)?;
 
+                // Anything other than the primary key on the other side means there can be more than one row.
+                if !to_field.is_primary_key {
+                    distinct = true;
+                }
+
                 joins.push(ResolvedJoin {
                     length: chain.len(),
+                    optional: table_registry.is_optional(&chain),
                     from_table: from_table.clone().resolve(context)?,
                     from_field: from_field.clone(),
                     from_instance_index: from_instance_index,
@@ -183,6 +346,11 @@ impl Select {
 
         joins.dedup();
 
+        let primary_key = match table.primary_key() {
+            Some(primary_key) => Some(context.metadata.lookup_field(primary_key)?.clone()),
+            None => None,
+        };
+
         let mut resolved_order = Vec::new();
         for (field_name, direction) in order.into_iter() {
             let field_id = table.lookup_field(field_name)?;
@@ -193,58 +361,153 @@ impl Select {
         Ok(ResolvedSelect {
             fields: resolved_fields,
             primary_table: resolved_table,
+            primary_key: primary_key,
             joins: joins,
+            distinct: distinct,
             filters: resolved_filters,
             order: resolved_order,
             limit: limit,
+            offset: offset,
+            after: after,
         })
     }
 }
 
 impl ResolvedSelect {
+    /// Run all of the external subqueries in the filters against their own instances
+    /// and fold the results back into this select.
+    ///
+    /// This has to happen before `as_sql` whenever a filter crosses a relation that isn't colocated.
+    pub async fn run_external_subqueries(mut self, context: &Context) -> Result<Self, DbrError> {
+        if let Some(filters) = self.filters.take() {
+            self.filters = Some(filters.run_external_subqueries(context).await?);
+        }
+
+        Ok(self)
+    }
+
+    /// Whether the filters can never match anything, e.g. an external subquery came back empty.
+    ///
+    /// In that case there is no point in running the query at all.
+    pub fn is_unsatisfiable(&self) -> bool {
+        match &self.filters {
+            Some(filters) => filters.is_unsatisfiable(),
+            None => false,
+        }
+    }
+
     /// Return pure sql and arguments
     ///
-    /// This will return `DbrError::UnresolvedQuery` if there is an external subquery somewhere still.
-    /// Those have to be run before the "parent" statement.
-    pub fn as_sql(mut self) -> Result<(String, BindValue), DbrError> {
+    /// This will return `DbrError::UnfinishedExternalSubquery` if there is an external subquery somewhere still.
+    /// Those have to be run before the "parent" statement, see `run_external_subqueries`.
+    pub fn as_sql(self) -> Result<(String, BindValue), DbrError> {
+        self.as_sql_selecting(None)
+    }
+
+    /// `SELECT COUNT(*)` of what this select would return.
+    pub fn as_count_sql(mut self) -> Result<(String, BindValue), DbrError> {
+        let columns = if self.distinct {
+            let primary_key = self
+                .primary_key
+                .clone()
+                .ok_or(DbrError::Unimplemented("missing primary key".to_owned()))?;
+            format!(
+                "COUNT(DISTINCT {})",
+                self.primary_table.column(None, &primary_key)
+            )
+        } else {
+            "COUNT(*)".to_owned()
+        };
+
+        self.order = Vec::new();
+        self.limit = None;
+        self.offset = None;
+        self.after = None;
+        self.as_sql_selecting(Some(columns))
+    }
+
+    /// `SELECT 1 ... LIMIT 1`, returns a row only if this select would return anything.
+    pub fn as_exists_sql(mut self) -> Result<(String, BindValue), DbrError> {
+        use sqlx::Arguments;
+
+        let mut limit = BindValue::default();
+        limit.add(1i64);
+
+        self.order = Vec::new();
+        self.limit = Some(limit);
+        self.offset = None;
+        self.after = None;
+        self.as_sql_selecting(Some("1".to_owned()))
+    }
+
+    /// `as_sql` with `columns` selected in place of the fields.
+    fn as_sql_selecting(
+        mut self,
+        columns: Option<String>,
+    ) -> Result<(String, BindValue), DbrError> {
         use sqlx::Arguments;
         let mut arguments = BindValue::default();
+        let dialect = self.primary_table.dialect();
         let schema_table = self.primary_table.instanced_with_schema(None);
-        let table = self.primary_table.instanced(None);
-        let fields = self
-            .fields
-            .iter()
-            .map(|field| format!("{table}.{field}", table = table, field = field.name))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let (filter_sql, filter_args) = match self.filters {
-            Some(filters) => {
-                let (filter_sql, filter_args) = filters.as_sql()?;
-                (format!("WHERE {}", filter_sql), filter_args)
+        let fields = match columns {
+            Some(columns) => columns,
+            None => {
+                let fields = self
+                    .fields
+                    .iter()
+                    .map(|field| self.primary_table.column(None, field))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                if self.distinct {
+                    format!("DISTINCT {}", fields)
+                } else {
+                    fields
+                }
             }
-            None => (String::new(), BindValue::default()),
         };
 
         let mut joins = Vec::new();
         self.joins.sort_by(|a, b| a.length.cmp(&b.length));
-        for join in self.joins {
+        for join in &self.joins {
             joins.push(join.as_sql());
         }
 
-        arguments.extend(filter_args);
+        let mut conditions = Vec::new();
+        if let Some(filters) = self.filters.take() {
+            let (filter_sql, filter_args) = filters.as_sql()?;
+            conditions.push(filter_sql);
+            arguments.extend(filter_args);
+        }
+
+        let mut order = self.order.clone();
+        if let Some(after) = self.after.take() {
+            order = self.keyset_order()?;
+            let (keyset_sql, keyset_args) = self.keyset_sql(&order, &after)?;
+            conditions.push(keyset_sql);
+            arguments.extend(keyset_args);
+        }
+
+        let where_str = if conditions.len() > 0 {
+            format!("WHERE {}", conditions.join(" AND "))
+        } else {
+            String::new()
+        };
 
-        let order_str = if self.order.len() > 0 {
+        let order_str = if order.len() > 0 {
             "ORDER BY ".to_owned()
-                + &self
-                    .order
+                + &order
                     .iter()
                     .map(|(field, dir)| {
+                        let descending = dir == &Some(OrderDirection::Descending);
                         let dir_str = match dir {
                             Some(OrderDirection::Ascending) => " ASC",
                             Some(OrderDirection::Descending) => " DESC",
                             _ => "",
                         };
-                        field.name.clone() + dir_str
+                        self.primary_table.column(None, field)
+                            + dir_str
+                            + dialect.nulls_first(descending)
                     })
                     .collect::<Vec<_>>()
                     .join(", ")
@@ -252,28 +515,213 @@ impl ResolvedSelect {
             String::new()
         };
 
-        let limit_str = match self.limit {
-            Some(limit) => {
+        let limit_str = match (self.limit, &self.offset) {
+            (Some(limit), _) => {
                 arguments.extend(limit);
                 "LIMIT ?".to_owned()
             }
+            (None, Some(_)) => match dialect.unbounded_limit() {
+                Some(unbounded) => format!("LIMIT {}", unbounded),
+                None => String::new(),
+            },
+            _ => String::new(),
+        };
+
+        let offset_str = match self.offset {
+            Some(offset) => {
+                arguments.extend(offset);
+                "OFFSET ?".to_owned()
+            }
             _ => String::new(),
         };
 
         let sql = format!(
-            "SELECT {fields} FROM {table} {joins} {where} {order} {limit}",
+            "SELECT {fields} FROM {table} {joins} {where} {order} {limit} {offset}",
             fields = fields,
             table = schema_table,
             joins = joins.join(" "),
-            r#where = filter_sql,
+            r#where = where_str,
             order = order_str,
             limit = limit_str,
+            offset = offset_str,
         )
         .trim()
         .to_owned();
 
         Ok((sql, arguments))
     }
+
+    /// Order of this select with ties broken by the primary key, so every record has a distinct position.
+    ///
+    /// These are the keys a page cursor holds the values of, see `Cursor`
+    pub fn keyset_order(&self) -> Result<Vec<(Field, Option<OrderDirection>)>, DbrError> {
+        let primary_key = self
+            .primary_key
+            .clone()
+            .ok_or(DbrError::Unimplemented("missing primary key".to_owned()))?;
+
+        let mut order = self.order.clone();
+        if !order.iter().any(|(field, _)| field.id == primary_key.id) {
+            order.push((primary_key, None));
+        }
+
+        Ok(order)
+    }
+
+    /// Rows that come after the cursor in `order`, `after` being the cursor's value of each key.
+    ///
+    /// e.g. for `order by name` this is `((name > ?) OR (name = ? AND id > ?))`
+    ///
+    /// NULLs sort before everything else (see `Dialect::nulls_first`) and can't be compared with `=`/`<`/`>`,
+    /// so they get their own `IS NULL`/`IS NOT NULL` checks.
+    fn keyset_sql(
+        &self,
+        order: &[(Field, Option<OrderDirection>)],
+        after: &[CursorValue],
+    ) -> Result<(String, BindValue), DbrError> {
+        if order.len() != after.len() {
+            return Err(DbrError::InvalidCursor(format!(
+                "expected {} order keys, got {}",
+                order.len(),
+                after.len()
+            )));
+        }
+
+        let mut alternatives = Vec::new();
+        let mut arguments = BindValue::default();
+        for (index, (field, direction)) in order.iter().enumerate() {
+            let descending = direction == &Some(OrderDirection::Descending);
+            let value = &after[index];
+
+            // Nothing comes after NULL going down.
+            if descending && value == &CursorValue::Null {
+                continue;
+            }
+
+            let mut comparisons = Vec::new();
+            for ((tied_field, _), tied_value) in order[..index].iter().zip(after) {
+                let column = self.primary_table.column(None, tied_field);
+                if tied_value == &CursorValue::Null {
+                    comparisons.push(format!("{} IS NULL", column));
+                } else {
+                    comparisons.push(format!("{} = ?", column));
+                    tied_value.bind(tied_field, &mut arguments)?;
+                }
+            }
+
+            let column = self.primary_table.column(None, field);
+            if value == &CursorValue::Null {
+                comparisons.push(format!("{} IS NOT NULL", column));
+            } else if descending {
+                comparisons.push(format!(
+                    "({column} < ? OR {column} IS NULL)",
+                    column = column
+                ));
+                value.bind(field, &mut arguments)?;
+            } else {
+                comparisons.push(format!("{} > ?", column));
+                value.bind(field, &mut arguments)?;
+            }
+
+            alternatives.push(format!("({})", comparisons.join(" AND ")));
+        }
+
+        if alternatives.is_empty() {
+            return Ok(("1 = 0".to_owned(), arguments));
+        }
+
+        Ok((format!("({})", alternatives.join(" OR ")), arguments))
+    }
+}
+
+impl ResolvedSelect {
+    /// Delete every record the filters of this select match and drop them from the record cache.
+    ///
+    /// The keys of the matching records are selected first and then deleted by key, inside of a
+    /// transaction of its own unless the context is in one already. That way exactly the records
+    /// that got deleted are evicted, and it doesn't matter whether the database can delete from a join.
+    ///
+    /// Returns the number of rows deleted.
+    pub async fn delete<T: DbrTable>(self, context: &Context) -> Result<u64, DbrError> {
+        if context.in_transaction() {
+            return self.delete_by_keys::<T>(context).await;
+        }
+
+        let transaction = context.begin_transaction();
+        match self.delete_by_keys::<T>(&transaction).await {
+            Ok(deleted) => {
+                transaction.commit().await?;
+                Ok(deleted)
+            }
+            Err(err) => {
+                transaction.rollback().await?;
+                Err(err)
+            }
+        }
+    }
+
+    async fn delete_by_keys<T: DbrTable>(self, context: &Context) -> Result<u64, DbrError> {
+        use sqlx::Arguments;
+
+        if self.is_unsatisfiable() {
+            return Ok(0);
+        }
+
+        let instance = self.primary_table.instance.clone();
+        let table = self.primary_table.name.clone();
+        let primary_key = self
+            .primary_key
+            .clone()
+            .ok_or(DbrError::Unimplemented("missing primary key".to_owned()))?;
+
+        let (keys_sql, keys_args) = self.as_keys_sql()?;
+        let rows = context.fetch_all(&instance, &keys_sql, keys_args).await?;
+        let keys = rows
+            .iter()
+            .map(|row| row.try_get::<T::Id, _>(0))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let dialect = instance.dialect();
+        let mut deleted = 0;
+        for chunk in keys.chunks(DELETE_CHUNK) {
+            let sql = dialect.delete_by_keys(
+                instance.info.database_name(),
+                &table,
+                &primary_key.name,
+                chunk.len(),
+            );
+
+            let mut arguments = BindValue::default();
+            for key in chunk {
+                arguments.add(key.clone());
+            }
+
+            deleted += context
+                .execute(&instance, &sql, arguments)
+                .await?
+                .rows_affected();
+        }
+
+        for key in keys {
+            context.remove_record::<T>(&instance, key)?;
+        }
+
+        Ok(deleted)
+    }
+
+    /// Primary keys of the records the filters of this select match, fields, order and limit are ignored.
+    pub fn as_keys_sql(mut self) -> Result<(String, BindValue), DbrError> {
+        let primary_key = self
+            .primary_key
+            .clone()
+            .ok_or(DbrError::Unimplemented("missing primary key".to_owned()))?;
+        self.fields = vec![primary_key];
+        self.order = Vec::new();
+        self.limit = None;
+        self.offset = None;
+        self.after = None;
+        self.as_sql()
+    }
 }
 
 pub enum FilterTree {
@@ -287,17 +735,116 @@ pub enum FilterTree {
     Predicate(FilterPredicate),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FilterOp {
     Eq,
     NotEq,
     Like,
     NotLike,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Between,
+    In,
+    NotIn,
+    IsNull,
+    IsNotNull,
+}
+
+impl FilterOp {
+    /// Comparison of `column` against the bound `value`.
+    pub fn as_sql(&self, column: &str, value: &FilterValue) -> String {
+        let comparison = |op: &str| format!("{column} {op} ?", column = column, op = op);
+        match self {
+            Self::Eq => comparison("="),
+            Self::NotEq => comparison("!="),
+            Self::Like => comparison("LIKE"),
+            Self::NotLike => comparison("NOT LIKE"),
+            Self::Lt => comparison("<"),
+            Self::LtEq => comparison("<="),
+            Self::Gt => comparison(">"),
+            Self::GtEq => comparison(">="),
+            Self::Between => format!("{} BETWEEN ? AND ?", column),
+            Self::In | Self::NotIn => {
+                let len = value.len();
+                // `IN ()` isn't valid sql, nothing is in an empty list.
+                match (self, len) {
+                    (Self::In, 0) => "1 = 0".to_owned(),
+                    (_, 0) => "1 = 1".to_owned(),
+                    (Self::In, _) => format!("{} IN ({})", column, vec!["?"; len].join(", ")),
+                    _ => format!("{} NOT IN ({})", column, vec!["?"; len].join(", ")),
+                }
+            }
+            Self::IsNull => format!("{} IS NULL", column),
+            Self::IsNotNull => format!("{} IS NOT NULL", column),
+        }
+    }
+}
+
+/// Values bound to a predicate, how many there are depends on the `FilterOp`.
+pub enum FilterValue {
+    /// `is null`/`is not null` don't need a value.
+    None,
+    Scalar(BindValue),
+    /// Lower and upper bound of a `between`.
+    Range(BindValue),
+    /// Collection bound to `in`/`not in`.
+    List {
+        values: BindValue,
+        len: usize,
+    },
+}
+
+impl FilterValue {
+    pub fn scalar<V>(value: V) -> Self
+    where
+        V: 'static + Send + for<'q> sqlx::Encode<'q, sqlx::Any> + sqlx::Type<sqlx::Any>,
+    {
+        use sqlx::Arguments;
+        let mut values = BindValue::default();
+        values.add(value);
+        Self::Scalar(values)
+    }
+
+    pub fn list<I>(items: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: 'static + Send + for<'q> sqlx::Encode<'q, sqlx::Any> + sqlx::Type<sqlx::Any>,
+    {
+        use sqlx::Arguments;
+        let mut values = BindValue::default();
+        let mut len = 0;
+        for item in items {
+            values.add(item);
+            len += 1;
+        }
+
+        Self::List { values, len }
+    }
+
+    /// Number of values bound.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Scalar(_) => 1,
+            Self::Range(_) => 2,
+            Self::List { len, .. } => *len,
+        }
+    }
+
+    pub fn into_arguments(self) -> BindValue {
+        match self {
+            Self::None => BindValue::default(),
+            Self::Scalar(values) | Self::Range(values) | Self::List { values, .. } => values,
+        }
+    }
 }
 
 pub struct FilterPredicate {
     pub path: RelationPath,
     pub op: FilterOp,
-    pub value: BindValue,
+    pub value: FilterValue,
 }
 
 impl FilterTree {
@@ -347,16 +894,32 @@ impl FilterTree {
         context: &Context,
         base_table_id: TableId,
         registry: &mut TableRegistry,
+    ) -> Result<ResolvedFilterTree, DbrError> {
+        self.resolve_joins(context, base_table_id, registry, false)
+    }
+
+    /// `optional` is set for anything under an `Or`, the joins for those can't drop rows.
+    fn resolve_joins(
+        self,
+        context: &Context,
+        base_table_id: TableId,
+        registry: &mut TableRegistry,
+        optional: bool,
     ) -> Result<ResolvedFilterTree, DbrError> {
         match self {
             Self::Or { left, right } => Ok(ResolvedFilterTree::Or {
-                left: Box::new(left.resolve(context, base_table_id, registry)?),
-                right: Box::new(right.resolve(context, base_table_id, registry)?),
+                left: Box::new(left.resolve_joins(context, base_table_id, registry, true)?),
+                right: Box::new(right.resolve_joins(context, base_table_id, registry, true)?),
             }),
             Self::And { children } => {
                 let mut resolved = Vec::new();
                 for child in children {
-                    resolved.push(child.resolve(context, base_table_id, registry)?);
+                    resolved.push(child.resolve_joins(
+                        context,
+                        base_table_id,
+                        registry,
+                        optional,
+                    )?);
                 }
 
                 Ok(ResolvedFilterTree::And { children: resolved })
@@ -381,15 +944,16 @@ impl FilterTree {
                         current_chain.push(relation.id);
 
                         // We only really care about the table index at the end of a relation chain.
-                        let (_from_index, to_index) = registry.add(context, &current_chain)?;
+                        let (_from_index, to_index) =
+                            registry.add(context, &current_chain, optional)?;
                         last_table_index = Some(to_index);
                     } else {
                         // we gots to do a subquery weeee
+                        //
+                        // The subquery selects the values of the related field so we can filter
+                        // our side of the relation with them once it has been run.
                         let mut subquery = Select::new(to_table.id);
-                        let primary_key = to_table
-                            .primary_key()
-                            .ok_or(DbrError::Unimplemented("missing primary key".to_owned()))?;
-                        subquery.fields.push(primary_key);
+                        subquery.fields.push(relation.to_field_id);
 
                         // Collect the rest of the relations and add it as a filter to the subquery, then resolve that.
                         subquery.filters = Some(FilterTree::Predicate(FilterPredicate {
@@ -403,8 +967,14 @@ impl FilterTree {
                         }));
 
                         let resolved_subquery = subquery.resolve(context)?;
+                        let from_field = context.metadata.lookup_field(relation.from_field_id)?;
                         return Ok(ResolvedFilterTree::Predicate(
-                            ResolvedFilter::ExternalSubquery(Box::new(resolved_subquery)),
+                            ResolvedFilter::ExternalSubquery {
+                                table: from_table.resolve(context)?,
+                                table_index: last_table_index,
+                                field: from_field.clone(),
+                                subquery: Box::new(resolved_subquery),
+                            },
                         ));
                     }
 
@@ -429,13 +999,23 @@ impl FilterTree {
 }
 
 pub enum ResolvedFilter {
-    ExternalSubquery(Box<ResolvedSelect>),
+    /// Subquery that has to be run on another instance before the parent query.
+    ///
+    /// `table`, `table_index` and `field` are our side of the relation that crossed over.
+    ExternalSubquery {
+        table: ResolvedTable,
+        table_index: Option<JoinedTableIndex>,
+        field: Field,
+        subquery: Box<ResolvedSelect>,
+    },
+    /// Finished external subquery that didn't return anything, nothing can match this.
+    Never,
     Predicate {
         table: ResolvedTable,
         table_index: Option<JoinedTableIndex>,
         field: Field,
         op: FilterOp,
-        value: BindValue,
+        value: FilterValue,
     },
 }
 
@@ -451,6 +1031,83 @@ pub enum ResolvedFilterTree {
 }
 
 impl ResolvedFilterTree {
+    /// See `ResolvedSelect::run_external_subqueries`
+    ///
+    /// Boxed since subqueries can have external subqueries of their own.
+    pub fn run_external_subqueries(
+        self,
+        context: &Context,
+    ) -> BoxFuture<'_, Result<Self, DbrError>> {
+        Box::pin(async move {
+            match self {
+                Self::Or { left, right } => Ok(Self::Or {
+                    left: Box::new(left.run_external_subqueries(context).await?),
+                    right: Box::new(right.run_external_subqueries(context).await?),
+                }),
+                Self::And { children } => {
+                    let mut finished = Vec::new();
+                    for child in children {
+                        finished.push(child.run_external_subqueries(context).await?);
+                    }
+
+                    Ok(Self::And { children: finished })
+                }
+                Self::Predicate(ResolvedFilter::ExternalSubquery {
+                    table,
+                    table_index,
+                    field,
+                    mut subquery,
+                }) => {
+                    if let Some(filters) = subquery.filters.take() {
+                        subquery.filters = Some(filters.run_external_subqueries(context).await?);
+                    }
+
+                    if subquery.is_unsatisfiable() {
+                        return Ok(Self::Predicate(ResolvedFilter::Never));
+                    }
+
+                    let instance = subquery.primary_table.instance.clone();
+                    let key_field = subquery.fields.first().cloned().ok_or_else(|| {
+                        DbrError::Unimplemented("external subquery without a field".to_owned())
+                    })?;
+                    let (sql, args) = subquery.as_sql()?;
+                    let rows = context.fetch_all(&instance, &sql, args).await?;
+
+                    let mut values = BindValue::default();
+                    let mut len = 0;
+                    for row in &rows {
+                        if bind_column(row, &key_field, &mut values)? {
+                            len += 1;
+                        }
+                    }
+
+                    // `IN` never matches a null, so rows with a null key don't count either.
+                    if len == 0 {
+                        return Ok(Self::Predicate(ResolvedFilter::Never));
+                    }
+
+                    Ok(Self::Predicate(ResolvedFilter::Predicate {
+                        table,
+                        table_index,
+                        field,
+                        op: FilterOp::In,
+                        value: FilterValue::List { values, len },
+                    }))
+                }
+                predicate @ Self::Predicate(_) => Ok(predicate),
+            }
+        })
+    }
+
+    pub fn is_unsatisfiable(&self) -> bool {
+        match self {
+            Self::Or { left, right } => left.is_unsatisfiable() && right.is_unsatisfiable(),
+            Self::And { children } => children.iter().any(|child| child.is_unsatisfiable()),
+            Self::Predicate(ResolvedFilter::Never) => true,
+            Self::Predicate(_) => false,
+        }
+    }
+
     pub fn as_sql(self) -> Result<(String, BindValue), DbrError> {
         use sqlx::Arguments;
         match self {
@@ -474,9 +1131,10 @@ impl ResolvedFilterTree {
                 Ok((sql.join(" AND "), args))
             }
             Self::Predicate(filter) => match filter {
-                ResolvedFilter::ExternalSubquery(_subquery) => {
+                ResolvedFilter::ExternalSubquery { .. } => {
                     Err(DbrError::UnfinishedExternalSubquery)
                 }
+                ResolvedFilter::Never => Ok(("1 = 0".to_owned(), BindValue::default())),
                 ResolvedFilter::Predicate {
                     table,
                     table_index,
@@ -484,41 +1142,186 @@ impl ResolvedFilterTree {
                     op,
                     value,
                 } => {
-                    let table_alias = table.instanced(table_index);
-                    let sql = match op {
-                        FilterOp::Eq => {
-                            format!(
-                                "{table}.{field} = ?",
-                                table = table_alias,
-                                field = field.name
-                            )
-                        }
-                        FilterOp::NotEq => {
-                            format!(
-                                "{table}.{field} != ?",
-                                table = table_alias,
-                                field = field.name
-                            )
-                        }
-                        FilterOp::Like => {
-                            format!(
-                                "{table}.{field} LIKE ?",
-                                table = table_alias,
-                                field = field.name
-                            )
-                        }
-                        FilterOp::NotLike => {
-                            format!(
-                                "{table}.{field} NOT LIKE ?",
-                                table = table_alias,
-                                field = field.name
-                            )
-                        }
-                    };
+                    let column = table.column(table_index, &field);
+                    let sql = op.as_sql(&column, &value);
+                    Ok((sql, value.into_arguments()))
+                }
+            },
+        }
+    }
+}
+
+/// Bind the first column of a subquery row selected from `field`, returns false if it was null.
+///
+/// The value is decoded as the type its data type maps to (see `codegen::rust_type`),
+/// whatever the Rust type of the key is on either side of the relation.
+fn bind_column(row: &AnyRow, field: &Field, values: &mut BindValue) -> Result<bool, DbrError> {
+    use sqlx::Arguments;
 
-                    Ok((sql, value))
+    macro_rules! bind {
+        ($ty:ty) => {
+            match row.try_get::<Option<$ty>, _>(0)? {
+                Some(value) => {
+                    values.add(value);
+                    true
                 }
+                None => false,
+            }
+        };
+    }
+
+    let bound = match field.kind() {
+        Some(DataType::BigInt) => bind!(i64),
+        Some(DataType::Int) if field.is_signed => bind!(i32),
+        Some(DataType::Int) => bind!(i64),
+        Some(DataType::MediumInt) => bind!(i32),
+        Some(DataType::SmallInt) if field.is_signed => bind!(i16),
+        Some(DataType::SmallInt) => bind!(i32),
+        Some(DataType::TinyInt) => bind!(i16),
+        Some(DataType::Bool) => bind!(bool),
+        Some(DataType::Float) => bind!(f32),
+        Some(DataType::Double) => bind!(f64),
+        Some(DataType::DateTime) => bind!(chrono::NaiveDateTime),
+        Some(DataType::Decimal) => bind!(Decimal),
+        Some(
+            DataType::Blob
+            | DataType::LongBlob
+            | DataType::MediumBlob
+            | DataType::TinyBlob
+            | DataType::Binary
+            | DataType::VarBinary,
+        ) => bind!(Vec<u8>),
+        Some(_) | None => bind!(String),
+    };
+
+    Ok(bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song_select(module: &str, order: &[(&str, Option<OrderDirection>)]) -> ResolvedSelect {
+        let metadata = Metadata::for_tests();
+        let table = metadata.tables.values().next().unwrap().clone();
+        let field = |name: &str| metadata.fields[&table.fields[name]].clone();
+
+        ResolvedSelect {
+            fields: vec![field("id"), field("name")],
+            primary_table: ResolvedTable {
+                instance: Arc::new(DbrInstance::for_tests(module)),
+                table: table.clone(),
             },
+            primary_key: Some(field("id")),
+            joins: Vec::new(),
+            distinct: false,
+            filters: None,
+            order: order
+                .iter()
+                .map(|(name, direction)| (field(name), direction.clone()))
+                .collect(),
+            limit: None,
+            offset: None,
+            after: None,
         }
     }
+
+    fn keyset(select: &ResolvedSelect, after: Vec<CursorValue>) -> String {
+        let order = select.keyset_order().unwrap();
+        select.keyset_sql(&order, &after).unwrap().0
+    }
+
+    #[tokio::test]
+    async fn keys_to_delete_ignore_order_and_paging() {
+        use sqlx::Arguments;
+
+        let mut select = song_select("Mysql", &[("name", None)]);
+        let mut limit = BindValue::default();
+        limit.add(10i64);
+        select.limit = Some(limit);
+        select.after = Some(vec![CursorValue::Text("b".into()), CursorValue::Int(3)]);
+
+        let (sql, _) = select.as_keys_sql().unwrap();
+        assert_eq!(
+            sql.split_whitespace().collect::<Vec<_>>().join(" "),
+            "SELECT `song`.`id` FROM `ops`.`song` AS `song`"
+        );
+    }
+
+    #[tokio::test]
+    async fn keyset_order_ends_with_the_primary_key() {
+        let select = song_select("Mysql", &[("name", None)]);
+        let order: Vec<_> = select
+            .keyset_order()
+            .unwrap()
+            .into_iter()
+            .map(|(field, _)| field.name)
+            .collect();
+        assert_eq!(order, vec!["name", "id"]);
+
+        let select = song_select("Mysql", &[("id", Some(OrderDirection::Descending))]);
+        assert_eq!(select.keyset_order().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn keyset_compares_against_the_cursor_values() {
+        let select = song_select("Mysql", &[("name", None)]);
+        assert_eq!(
+            keyset(
+                &select,
+                vec![CursorValue::Text("b".into()), CursorValue::Int(3)]
+            ),
+            "((`song`.`name` > ?) OR (`song`.`name` = ? AND `song`.`id` > ?))"
+        );
+    }
+
+    #[tokio::test]
+    async fn keyset_descends_past_nulls() {
+        let select = song_select("Mysql", &[("name", Some(OrderDirection::Descending))]);
+        assert_eq!(
+            keyset(&select, vec![CursorValue::Text("b".into()), CursorValue::Int(3)]),
+            "(((`song`.`name` < ? OR `song`.`name` IS NULL)) OR (`song`.`name` = ? AND `song`.`id` > ?))"
+        );
+    }
+
+    #[tokio::test]
+    async fn keyset_handles_null_cursor_values() {
+        let select = song_select("Mysql", &[("name", None)]);
+        assert_eq!(
+            keyset(&select, vec![CursorValue::Null, CursorValue::Int(3)]),
+            "((`song`.`name` IS NOT NULL) OR (`song`.`name` IS NULL AND `song`.`id` > ?))"
+        );
+
+        // Nothing sorts below NULL, only the ties are left.
+        let select = song_select("Mysql", &[("name", Some(OrderDirection::Descending))]);
+        assert_eq!(
+            keyset(&select, vec![CursorValue::Null, CursorValue::Int(3)]),
+            "((`song`.`name` IS NULL AND `song`.`id` > ?))"
+        );
+    }
+
+    #[tokio::test]
+    async fn keyset_rejects_cursors_for_another_order() {
+        let select = song_select("Mysql", &[("name", None)]);
+        let order = select.keyset_order().unwrap();
+        let result = select.keyset_sql(&order, &[CursorValue::Int(3)]);
+        assert!(matches!(result, Err(DbrError::InvalidCursor(_))));
+    }
+
+    #[tokio::test]
+    async fn paged_select_orders_nulls_first_on_postgres() {
+        let mut select = song_select("Pg", &[("name", Some(OrderDirection::Descending))]);
+        select.after = Some(vec![CursorValue::Text("b".into()), CursorValue::Int(3)]);
+
+        let (sql, _) = select.as_sql().unwrap();
+        assert!(sql.contains("FROM \"song\" AS \"song\""), "{}", sql);
+        assert!(!sql.contains("JOIN"), "{}", sql);
+        assert!(
+            sql.ends_with(
+                "ORDER BY \"song\".\"name\" DESC NULLS LAST, \"song\".\"id\" NULLS FIRST"
+            ),
+            "{}",
+            sql
+        );
+    }
 }
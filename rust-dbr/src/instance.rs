@@ -4,7 +4,7 @@ use std::{
     sync::{Arc, RwLock},
 };
 
-use sqlx::{ MySql};
+use sqlx::MySql;
 
 use crate::prelude::*;
 
@@ -41,15 +41,16 @@ pub struct DbrInstanceInfo {
     password: String,
     host: String,
 
+    /// Path to the database file, only used by SQLite instances.
+    #[sqlx(rename = "dbfile")]
+    database_file: Option<String>,
+
     /// Extraneous fields here for the sake of modeling the dbr.dbr_instances table.
     ///
     /// Could be useful for something in the future, but I'm not entirely sure yet.
     ///
     /// Feel free to move them above and add a comment if you think otherwise!
     #[allow(dead_code)]
-    #[sqlx(rename = "dbfile")]
-    database_file: Option<String>,
-    #[allow(dead_code)]
     #[sqlx(rename = "readonly")]
     read_only: Option<bool>,
 }
@@ -71,9 +72,20 @@ impl DbrInstanceInfo {
     }
 
     pub fn connection_host_uri(&self) -> String {
+        let module = self.instance_module();
+        if let Ok(InstanceModule::SQLite) = module {
+            // No host to speak of, the file is the closest thing we have.
+            return format!(
+                "sqlite://{file}",
+                file = self.database_file().clone().unwrap_or_default()
+            );
+        }
+
         format!(
             "{from}://{user}:{pass}@{host}",
-            from = self.module(),
+            from = module
+                .map(|module| module.scheme().to_owned())
+                .unwrap_or_else(|_| self.module()),
             user = self.username(),
             pass = self.password(),
             host = self.host(),
@@ -81,6 +93,10 @@ impl DbrInstanceInfo {
     }
 
     pub fn connection_uri(&self) -> String {
+        if let Ok(InstanceModule::SQLite) = self.instance_module() {
+            return self.connection_host_uri();
+        }
+
         format!(
             "{uri}/{db}",
             uri = self.connection_host_uri(),
@@ -88,14 +104,23 @@ impl DbrInstanceInfo {
         )
     }
 
-    // Are these a part of the same database?
+    // Can a query on one of these reach the tables of the other?
     //
     // We don't include the "schema" here because you can have cases like
     // constants and directory being in the same database but a different schema.
     //
-    // Probaby should check if this is fine.
+    // MySQL only needs the same server since tables get qualified with their database,
+    // the other backends can't do that so they need to be the very same database.
     pub fn colocated<O: Borrow<Self>>(&self, other: O) -> bool {
-        self.connection_host_uri() == other.borrow().connection_host_uri()
+        let other = other.borrow();
+        if self.connection_host_uri() != other.connection_host_uri() {
+            return false;
+        }
+
+        match self.instance_module() {
+            Ok(module) if Dialect::from(module).crosses_databases() => true,
+            _ => self.database_name() == other.database_name(),
+        }
     }
 
     pub fn id(&self) -> DbrInstanceId {
@@ -106,18 +131,22 @@ impl DbrInstanceInfo {
         &self.schema
     }
 
+    pub fn schema_id(&self) -> SchemaId {
+        self.schema_id
+    }
+
     pub fn class(&self) -> &String {
         &self.class
     }
 
-    /*
-    pub fn module(&self) -> &InstanceModule {
-        &self.module
-    } */
     pub fn module(&self) -> String {
         self.module.to_owned().to_lowercase()
     }
 
+    pub fn instance_module(&self) -> Result<InstanceModule, DbrError> {
+        InstanceModule::from_name(&self.module)
+    }
+
     pub fn username(&self) -> &String {
         &self.username
     }
@@ -141,9 +170,13 @@ impl DbrInstanceInfo {
     pub fn tag(&self) -> &Option<String> {
         &self.tag
     }
+
+    pub fn database_file(&self) -> &Option<String> {
+        &self.database_file
+    }
 }
 
-#[derive(sqlx::Type, Debug, Clone)]
+#[derive(sqlx::Type, Debug, Copy, Clone, PartialEq, Eq)]
 #[sqlx(type_name = "VARCHAR")]
 #[sqlx(rename_all = "lowercase")]
 pub enum InstanceModule {
@@ -152,6 +185,27 @@ pub enum InstanceModule {
     Postgres,
 }
 
+impl InstanceModule {
+    /// Module as it is spelled in dbr_instances, e.g. `Mysql`, `SQLite` or `Pg`
+    pub fn from_name(name: &str) -> Result<Self, DbrError> {
+        match name.to_lowercase().as_str() {
+            "mysql" => Ok(Self::MySql),
+            "sqlite" => Ok(Self::SQLite),
+            "pg" | "postgres" | "postgresql" => Ok(Self::Postgres),
+            _ => Err(DbrError::UnsupportedModule(name.to_owned())),
+        }
+    }
+
+    /// Scheme of the connection uri sqlx expects for this backend.
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            Self::MySql => "mysql",
+            Self::SQLite => "sqlite",
+            Self::Postgres => "postgres",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DbrInstances {
     // handle, tag -> dbr instance
@@ -255,19 +309,84 @@ impl DbrInstances {
 #[derive(Debug)]
 pub struct DbrInstance {
     pub info: DbrInstanceInfo,
+    pub module: InstanceModule,
     pub cache: DbrRecordCache,
-    pub pool: sqlx::MySqlPool,
+    pub pool: sqlx::AnyPool,
 }
 
 impl DbrInstance {
+    pub fn dialect(&self) -> Dialect {
+        self.module.into()
+    }
+
     pub async fn new(info: DbrInstanceInfo) -> Result<Self, DbrError> {
+        let module = info.instance_module()?;
+        if module == InstanceModule::SQLite && info.database_file().is_none() {
+            return Err(DbrError::MissingDatabaseFile(info.id()));
+        }
+
         let uri = info.connection_uri();
-        let pool = sqlx::MySqlPool::connect(&uri).await?;
+        let pool = sqlx::AnyPool::connect(&uri).await?;
 
         Ok(Self {
             info: info,
+            module: module,
             cache: DbrRecordCache::new(),
             pool: pool,
         })
     }
 }
+
+#[cfg(test)]
+impl DbrInstanceInfo {
+    pub(crate) fn for_tests(module: &str, host: &str, database_name: &str) -> Self {
+        Self {
+            id: DbrInstanceId(1),
+            module: module.to_owned(),
+            schema: "ops".to_owned(),
+            schema_id: SchemaId::for_tests(1),
+            class: "master".to_owned(),
+            tag: None,
+            database_name: database_name.to_owned(),
+            username: "dbr".to_owned(),
+            password: "secret".to_owned(),
+            host: host.to_owned(),
+            database_file: None,
+            read_only: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl DbrInstance {
+    /// `ops` instance for `module` that never connects, enough to generate SQL with.
+    ///
+    /// Has to be made inside of a runtime.
+    pub(crate) fn for_tests(module: &str) -> Self {
+        Self {
+            info: DbrInstanceInfo::for_tests(module, "localhost", "ops"),
+            module: InstanceModule::from_name(module).unwrap(),
+            cache: DbrRecordCache::new(),
+            pool: sqlx::AnyPool::connect_lazy("sqlite::memory:").unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mysql_databases_on_one_server_are_colocated() {
+        let ops = DbrInstanceInfo::for_tests("Mysql", "db1", "ops");
+        assert!(ops.colocated(DbrInstanceInfo::for_tests("Mysql", "db1", "config")));
+        assert!(!ops.colocated(DbrInstanceInfo::for_tests("Mysql", "db2", "ops")));
+    }
+
+    #[test]
+    fn postgres_databases_need_to_match() {
+        let ops = DbrInstanceInfo::for_tests("Pg", "db1", "ops");
+        assert!(ops.colocated(DbrInstanceInfo::for_tests("Pg", "db1", "ops")));
+        assert!(!ops.colocated(DbrInstanceInfo::for_tests("Pg", "db1", "config")));
+    }
+}
@@ -1,39 +1,91 @@
 pub mod cache;
+pub mod codegen;
 pub mod context;
+pub mod decimal;
+pub mod dialect;
 pub mod error;
 pub mod filter;
 pub mod instance;
 pub mod metadata;
 pub mod model;
+pub mod observer;
+pub mod pagination;
+pub mod relation;
+pub mod scan;
 pub mod table;
+pub mod transaction;
+pub mod translator;
+pub mod validation;
+
+/// For the `DateTime` fields of generated structs, see `codegen::rust_type`
+pub use chrono;
 
 pub fn _assert_bindable<
     'a,
-    T: std::marker::Send + ::sqlx::Encode<'a, ::sqlx::MySql> + ::sqlx::Type<::sqlx::MySql>,
+    T: std::marker::Send + ::sqlx::Encode<'a, ::sqlx::Any> + ::sqlx::Type<::sqlx::Any>,
 >(
     _t: T,
 ) {
     // just here for compiler errors.
 }
 
+pub fn _assert_bindable_list<'a, I>(_t: &I)
+where
+    I: IntoIterator,
+    I::Item: std::marker::Send + ::sqlx::Encode<'a, ::sqlx::Any> + ::sqlx::Type<::sqlx::Any>,
+{
+    // just here for compiler errors.
+}
+
+/// `table == expected` in a const, for the fetch macros to make sure they checked the right table.
+pub const fn _same_table(table: &str, expected: &str) -> bool {
+    let (table, expected) = (table.as_bytes(), expected.as_bytes());
+    if table.len() != expected.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < table.len() {
+        if table[i] != expected[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
 pub mod prelude {
     pub use crate::cache::{DbrRecordCache, RecordMetadata};
     pub use crate::context::{
         Context, JoinedTableIndex, RelationChain, RelationPath, TableRegistry,
     };
+    pub use crate::decimal::Decimal;
+    pub use crate::dialect::Dialect;
     pub use crate::error::DbrError;
-    pub use crate::filter::{FilterOp, FilterPredicate, FilterTree, OrderDirection, Select};
-    pub use crate::instance::{DbrInstance, DbrInstanceId, DbrInstanceInfo, DbrInstances};
+    pub use crate::filter::{
+        FilterOp, FilterPredicate, FilterTree, FilterValue, OrderDirection, Select,
+    };
+    pub use crate::instance::{
+        DbrInstance, DbrInstanceId, DbrInstanceInfo, DbrInstances, InstanceModule,
+    };
     pub use crate::metadata::{
-        Field, FieldId, FieldIdentifier, Metadata, Relation, RelationId, Schema, SchemaId,
-        SchemaIdentifier, Table, TableId, TableIdentifier,
+        DataType, EnumOption, Field, FieldId, FieldIdentifier, Metadata, MetadataSnapshot,
+        Relation, RelationId, Schema, SchemaId, SchemaIdentifier, Table, TableId, TableIdentifier,
     };
     pub use crate::model::{Active, ActiveModel, PartialModel};
+    pub use crate::observer::{QueryEvent, QueryObserver, StatementKind};
+    pub use crate::pagination::{Cursor, CursorValue, Page};
+    pub use crate::relation::RelatedTo;
+    pub use crate::scan::{MetadataChanges, ScannedTable};
     pub use crate::table::DbrTable;
+    pub use crate::translator::{Dollars, Enum, Percent, Translated, Translator, UnixTime};
+    pub use crate::validation::{FieldValue, ToFieldValue, ValidationFailure, ValidationReason};
 }
 
 pub use prelude::{
-    Active, ActiveModel, Context, DbrError, DbrTable, FilterOp, FilterPredicate, FilterTree,
-    JoinedTableIndex, Metadata, OrderDirection, PartialModel, RelationChain, RelationId,
-    RelationPath, SchemaIdentifier, Select, TableIdentifier, TableRegistry,
+    Active, ActiveModel, Context, Cursor, DbrError, DbrTable, Dialect, FilterOp, FilterPredicate,
+    FilterTree, FilterValue, JoinedTableIndex, Metadata, OrderDirection, Page, PartialModel,
+    RelatedTo, RelationChain, RelationId, RelationPath, SchemaIdentifier, Select, TableIdentifier,
+    TableRegistry,
 };
@@ -1,21 +1,289 @@
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    //let sqlite = sqlite::open(":memory:");
-
-    //let val: Vec<String> = conn.query("SHOW TABLES")?;
-
-    //let mut instances = Instance::fetch_all(&mut conn)?;
-    /* dbg!(&instances);
-
-       let instance = instances
-           .iter()
-           .filter(|instance| instance.tag == Some("c1".to_owned()))
-           .next()
-           .unwrap();
-       dbg!(&instance);
-       let mut c1_conn = instance.connection()?;
-       let values: Vec<mysql::Row> = c1_conn.query("SELECT * FROM customer_order")?;
-       dbg!(values);
-    */
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::{Parser, Subcommand};
+use rust_dbr::{codegen, metadata::Metadata, prelude::*};
+
+/// Tools for working with dbr metadata.
+#[derive(Parser, Debug)]
+#[clap(name = "rust-dbr", version)]
+struct Cli {
+    /// Connection url of the dbr metadata database.
+    #[clap(long, env = "DBR_URL", global = true)]
+    dbr_url: Option<String>,
+
+    /// Metadata file saved with `Metadata::save_to_path`, used instead of the dbr database.
+    #[clap(long, global = true)]
+    metadata: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print `DbrTable` structs for the tables in the metadata.
+    Codegen {
+        /// Only generate these schemas, all of them by default.
+        #[clap(long = "schema")]
+        schemas: Vec<String>,
+
+        /// Write to this file instead of stdout.
+        #[clap(long, short)]
+        out: Option<PathBuf>,
+    },
+
+    /// List the instances in dbr_instances, passwords are redacted.
+    Instances,
+
+    /// Show the fields, relations and instances of a table.
+    Describe {
+        /// Table to describe, e.g. `ops.customer_order`
+        table: String,
+    },
+
+    /// Try to connect to every instance and report the ones that fail.
+    Check {
+        /// Seconds to wait on each instance before giving up.
+        #[clap(long, default_value = "10")]
+        timeout: u64,
+    },
+}
+
+impl Cli {
+    async fn dbr_pool(&self) -> Result<sqlx::MySqlPool, Box<dyn Error>> {
+        match &self.dbr_url {
+            Some(url) => Ok(sqlx::mysql::MySqlPool::connect(url).await?),
+            None => Err("--dbr-url (DBR_URL) is needed".into()),
+        }
+    }
+
+    async fn metadata(&self) -> Result<Metadata, Box<dyn Error>> {
+        match (&self.metadata, &self.dbr_url) {
+            (Some(path), _) => Ok(Metadata::load_from_path(path)?),
+            (None, Some(_)) => {
+                let pool = self.dbr_pool().await?;
+                let connection = pool.acquire().await?;
+                Ok(Metadata::fetch(connection).await?)
+            }
+            (None, None) => Err("either --metadata or --dbr-url (DBR_URL) is needed".into()),
+        }
+    }
+
+    async fn instances(&self) -> Result<Vec<DbrInstanceInfo>, Box<dyn Error>> {
+        let pool = self.dbr_pool().await?;
+        let mut instances = DbrInstanceInfo::fetch_all(&pool).await?;
+        instances.sort_by_key(|info| info.id());
+        Ok(instances)
+    }
+}
+
+fn instances(instances: &[DbrInstanceInfo]) {
+    println!(
+        "{:<6} {:<8} {:<16} {:<8} {:<10} {:<24} {:<16} {:<12} {}",
+        "id", "module", "handle", "tag", "class", "host", "database", "username", "password"
+    );
+    for info in instances {
+        println!(
+            "{:<6} {:<8} {:<16} {:<8} {:<10} {:<24} {:<16} {:<12} {}",
+            info.id().0,
+            info.module(),
+            info.schema(),
+            info.tag().as_deref().unwrap_or("-"),
+            info.class(),
+            info.database_file().as_deref().unwrap_or(info.host()),
+            info.database_name(),
+            info.username(),
+            if info.password().is_empty() {
+                ""
+            } else {
+                "********"
+            },
+        );
+    }
+}
+
+fn describe(
+    metadata: &Metadata,
+    instances: &[DbrInstanceInfo],
+    name: &str,
+) -> Result<(), Box<dyn Error>> {
+    let (schema_name, table_name) = name
+        .split_once('.')
+        .ok_or_else(|| format!("expected schema.table, got {}", name))?;
+
+    let schema = metadata.lookup_schema(SchemaIdentifier::Name(schema_name.to_owned()))?;
+    let table_id = schema.lookup_table_by_name(table_name.to_owned())?;
+    let table = metadata.lookup_table(*table_id)?;
+
+    println!("{}.{} (table {:?})", schema.name, table.name, table.id);
+
+    let mut fields: Vec<&Field> = table
+        .fields
+        .values()
+        .map(|field_id| metadata.lookup_field(*field_id))
+        .collect::<Result<_, _>>()?;
+    fields.sort_by_key(|field| field.id);
+
+    println!();
+    println!("fields:");
+    for field in &fields {
+        let kind = match field.kind() {
+            Some(kind) if field.max_value > 0 => format!("{:?}({})", kind, field.max_value),
+            Some(kind) => format!("{:?}", kind),
+            None => format!("unknown type {}", field.data_type),
+        };
+
+        let mut notes = Vec::new();
+        if field.is_primary_key {
+            notes.push("primary key".to_owned());
+        }
+        if field.is_nullable {
+            notes.push("nullable".to_owned());
+        }
+        if field.kind().map_or(false, |kind| kind.is_numeric()) && !field.is_signed {
+            notes.push("unsigned".to_owned());
+        }
+        if let Some(translator) = field.translator() {
+            notes.push(format!("{:?}", translator));
+        }
+        if let Some(regex) = &field.regex {
+            notes.push(format!("/{}/", regex));
+        }
+
+        println!(
+            "  {:<24} {:<20} {}  -> {}",
+            field.name,
+            kind,
+            notes.join(", "),
+            codegen::rust_type(field)
+        );
+    }
+
+    let field_path = |field_id: FieldId| -> Result<String, DbrError> {
+        let field = metadata.lookup_field(field_id)?;
+        let table = metadata.lookup_table(field.table_id)?;
+        let schema = metadata.lookup_schema(SchemaIdentifier::Id(table.schema_id))?;
+        Ok(format!("{}.{}.{}", schema.name, table.name, field.name))
+    };
+
+    let mut relations: Vec<&Relation> = metadata.relations.values().collect();
+    relations.sort_by_key(|relation| relation.id);
+
+    println!();
+    println!("relations out:");
+    for relation in relations
+        .iter()
+        .filter(|relation| relation.from_table_id == table.id)
+    {
+        println!(
+            "  {} -> {}",
+            field_path(relation.from_field_id)?,
+            field_path(relation.to_field_id)?
+        );
+    }
+
+    println!();
+    println!("relations in:");
+    for relation in relations
+        .iter()
+        .filter(|relation| relation.to_table_id == table.id)
+    {
+        println!(
+            "  {} -> {}",
+            field_path(relation.from_field_id)?,
+            field_path(relation.to_field_id)?
+        );
+    }
+
+    println!();
+    println!("instances:");
+    for info in instances
+        .iter()
+        .filter(|info| info.schema_id() == schema.id)
+    {
+        println!(
+            "  tag {:<8} instance {:<6} {} {}/{}",
+            info.tag().as_deref().unwrap_or("-"),
+            info.id().0,
+            info.module(),
+            info.database_file().as_deref().unwrap_or(info.host()),
+            info.database_name(),
+        );
+    }
+
+    Ok(())
+}
+
+async fn check(instances: Vec<DbrInstanceInfo>, timeout: Duration) -> usize {
+    let mut failures = 0;
+    for info in instances {
+        let label = format!(
+            "instance {} ({}{})",
+            info.id().0,
+            info.schema(),
+            info.tag()
+                .as_ref()
+                .map(|tag| format!(", {}", tag))
+                .unwrap_or_default()
+        );
+
+        let started = Instant::now();
+        let result = tokio::time::timeout(timeout, async {
+            let instance = DbrInstance::new(info).await?;
+            sqlx::query("SELECT 1").execute(&instance.pool).await?;
+            Ok::<_, DbrError>(())
+        })
+        .await;
+
+        match result {
+            Ok(Ok(())) => println!("ok     {} in {:?}", label, started.elapsed()),
+            Ok(Err(err)) => {
+                failures += 1;
+                println!("FAILED {}: {}", label, err);
+            }
+            Err(_) => {
+                failures += 1;
+                println!("FAILED {}: timed out after {:?}", label, timeout);
+            }
+        }
+    }
+
+    failures
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    match &cli.command {
+        Command::Codegen { schemas, out } => {
+            let metadata = cli.metadata().await?;
+            let source = codegen::generate(&metadata, schemas)?;
+            match out {
+                Some(path) => std::fs::write(path, source)?,
+                None => print!("{}", source),
+            }
+        }
+        Command::Instances => instances(&cli.instances().await?),
+        Command::Describe { table } => {
+            let metadata = cli.metadata().await?;
+            // Instances are only in the dbr database, skip them when going off of a file.
+            let instances = match &cli.dbr_url {
+                Some(_) => cli.instances().await?,
+                None => Vec::new(),
+            };
+            describe(&metadata, &instances, table)?;
+        }
+        Command::Check { timeout } => {
+            let failures = check(cli.instances().await?, Duration::from_secs(*timeout)).await;
+            if failures > 0 {
+                return Err(format!("{} instances failed", failures).into());
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
+use std::path::Path;
 
 use derive_more::Deref;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use sqlx::{Executor, MySql};
 
 use crate::prelude::*;
@@ -70,6 +73,12 @@ impl std::fmt::Display for MissingField {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum EnumIdentifier {
+    Id(u32),
+    Handle(String),
+}
+
 #[derive(Debug)]
 pub enum MetadataError {
     MissingRelation(MissingRelation),
@@ -81,6 +90,10 @@ pub enum MetadataError {
     MissingSchema {
         schema: SchemaIdentifier,
     },
+    MissingEnum {
+        field: FieldId,
+        option: EnumIdentifier,
+    },
 }
 
 impl std::fmt::Display for MetadataError {
@@ -98,28 +111,114 @@ impl std::fmt::Display for MetadataError {
             Self::MissingRelation(missing) => {
                 write!(f, "{}", missing)
             }
+            Self::MissingEnum { field, option } => {
+                write!(f, "missing enum option {:?} for field {}", option, field.0)
+            }
         }
     }
 }
 
 impl std::error::Error for MetadataError {}
 
-#[derive(sqlx::Type, Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    sqlx::Type,
+    Serialize,
+    Deserialize,
+    Default,
+    Debug,
+    Copy,
+    Clone,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
 #[sqlx(transparent)]
+#[serde(transparent)]
 pub struct SchemaId(u32);
 
-#[derive(sqlx::Type, Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg(test)]
+impl SchemaId {
+    pub(crate) fn for_tests(id: u32) -> Self {
+        Self(id)
+    }
+}
+
+#[derive(
+    sqlx::Type,
+    Serialize,
+    Deserialize,
+    Default,
+    Debug,
+    Copy,
+    Clone,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
 #[sqlx(transparent)]
+#[serde(transparent)]
 pub struct FieldId(u32);
 
-#[derive(sqlx::Type, Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    sqlx::Type,
+    Serialize,
+    Deserialize,
+    Default,
+    Debug,
+    Copy,
+    Clone,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
 #[sqlx(transparent)]
+#[serde(transparent)]
 pub struct TableId(u32);
 
-#[derive(sqlx::Type, Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    sqlx::Type,
+    Serialize,
+    Deserialize,
+    Default,
+    Debug,
+    Copy,
+    Clone,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
 #[sqlx(transparent)]
+#[serde(transparent)]
 pub struct RelationId(u32);
 
+// Ids for rows we make ourselves, e.g. from `scan`, they are only picked by us
+// before being written back so the new rows can point at each other.
+impl TableId {
+    pub(crate) fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
+}
+
+impl FieldId {
+    pub(crate) fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
+}
+
+impl RelationId {
+    pub(crate) fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
+}
+
 // The metadata is effectively a tree, so lets just have all the data owned
 // in the top level with weak reference counted pointers internally.
 #[derive(Debug, Clone)]
@@ -129,6 +228,12 @@ pub struct Metadata {
     pub fields: HashMap<FieldId, Field>,
     pub relations: HashMap<RelationId, Relation>,
 
+    /// Options of enum translated fields, in their sort order.
+    pub enums: HashMap<FieldId, Vec<EnumOption>>,
+
+    /// `Field::regex` compiled when the metadata is built, fields without a valid one are left out.
+    pub patterns: HashMap<FieldId, Regex>,
+
     pub named_schemas: HashMap<String, SchemaId>,
 }
 
@@ -141,8 +246,69 @@ impl Metadata {
         let tables = TableInfo::fetch_all(&mut executor).await?;
         let fields = Field::fetch_all(&mut executor).await?;
         let relations = Relation::fetch_all(&mut executor).await?;
+        let enums = EnumOption::fetch_all(&mut executor).await?;
+
+        Self::build(schemas, tables, fields, relations, enums)
+    }
+
+    /// Fetch the metadata from a live dbr database and save it to `path`, see `save_to_path`
+    pub async fn export_to_path<E, P>(executor: E, path: P) -> Result<Self, DbrError>
+    where
+        for<'c> &'c mut E: Executor<'c, Database = MySql>,
+        P: AsRef<Path>,
+    {
+        let metadata = Self::fetch(executor).await?;
+        metadata.save_to_path(path)?;
+        Ok(metadata)
+    }
+
+    /// Load metadata saved with `save_to_path`, no dbr database needed.
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Self, DbrError> {
+        let file = std::fs::File::open(path)?;
+        let snapshot: MetadataSnapshot = serde_json::from_reader(std::io::BufReader::new(file))?;
+        snapshot.into_metadata()
+    }
+
+    /// Save the metadata as JSON.
+    ///
+    /// Everything is sorted by id so the file diffs cleanly if it is kept in git.
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), DbrError> {
+        let file = std::fs::File::create(path)?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &self.snapshot())?;
+        std::io::Write::flush(&mut writer)?;
+        Ok(())
+    }
 
-        Self::build(schemas, tables, fields, relations)
+    /// The rows the metadata was built from, see `build`
+    pub fn snapshot(&self) -> MetadataSnapshot {
+        let mut schemas: Vec<_> = self
+            .schemas
+            .values()
+            .map(|schema| schema.info.clone())
+            .collect();
+        let mut tables: Vec<_> = self
+            .tables
+            .values()
+            .map(|table| table.info.clone())
+            .collect();
+        let mut fields: Vec<_> = self.fields.values().cloned().collect();
+        let mut relations: Vec<_> = self.relations.values().cloned().collect();
+        let mut enums: Vec<_> = self.enums.values().flatten().cloned().collect();
+
+        schemas.sort_by_key(|schema| schema.id);
+        tables.sort_by_key(|table| table.id);
+        fields.sort_by_key(|field| field.id);
+        relations.sort_by_key(|relation| relation.id);
+        enums.sort_by_key(|option| (option.field_id, option.sort_value, option.id));
+
+        MetadataSnapshot {
+            schemas,
+            tables,
+            fields,
+            relations,
+            enums,
+        }
     }
 
     pub fn build(
@@ -150,11 +316,13 @@ impl Metadata {
         table_list: Vec<TableInfo>,
         field_list: Vec<Field>,
         relation_list: Vec<Relation>,
+        enum_list: Vec<EnumOption>,
     ) -> Result<Self, DbrError> {
         let mut schemas = HashMap::new();
         let mut tables = HashMap::new();
         let mut fields = HashMap::new();
         let mut relations = HashMap::new();
+        let mut enums: HashMap<FieldId, Vec<EnumOption>> = HashMap::new();
 
         let mut named_schemas = HashMap::new();
 
@@ -179,6 +347,7 @@ impl Metadata {
                 Table {
                     info: table,
                     primary_key: None,
+                    version_field: None,
                     fields: HashMap::new(),
                     relations: HashMap::new(),
                 },
@@ -193,11 +362,21 @@ impl Metadata {
             relations.insert(relation.id, relation);
         }
 
+        for option in enum_list {
+            enums.entry(option.field_id).or_default().push(option);
+        }
+
+        for options in enums.values_mut() {
+            options.sort_by_key(|option| (option.sort_value, option.id));
+        }
+
         let mut metadata = Self {
             schemas,
             tables,
             fields,
             relations,
+            enums,
+            patterns: HashMap::new(),
 
             named_schemas,
         };
@@ -208,6 +387,12 @@ impl Metadata {
 
     // Build internal connections between schemas, tables, fields, and relations.
     pub fn rebuild(&mut self) {
+        self.patterns = self
+            .fields
+            .iter()
+            .filter_map(|(field_id, field)| Some((*field_id, field.compile_pattern()?)))
+            .collect();
+
         for (table_id, table) in &self.tables {
             if let Some(schema) = self.schemas.get_mut(&table.schema_id) {
                 schema.tables.insert(table.name.clone(), *table_id);
@@ -220,6 +405,15 @@ impl Metadata {
                 if field.is_primary_key {
                     table.primary_key = Some(*field_id);
                 }
+
+                let numeric = field.kind().map_or(false, |kind| kind.is_numeric());
+                if table.version_field.is_none()
+                    && field.name == VERSION_FIELD
+                    && numeric
+                    && !field.is_primary_key
+                {
+                    table.version_field = Some(*field_id);
+                }
             }
         }
 
@@ -271,6 +465,64 @@ impl Metadata {
             .ok_or(MetadataError::MissingField(MissingField::Id(field)).into())
     }
 
+    /// Table backing a `DbrTable` implementation.
+    pub fn lookup_dbr_table<T: DbrTable>(&self) -> Result<&Table, DbrError> {
+        let schema = self.lookup_schema(SchemaIdentifier::Name(T::schema().to_owned()))?;
+        let table_id = schema.lookup_table_by_name(T::table_name().to_owned())?;
+        self.lookup_table(*table_id)
+    }
+
+    /// Use `field` for the optimistic locking of `table_id` instead of the one named `VERSION_FIELD`
+    pub fn set_version_field(&mut self, table_id: TableId, field: &str) -> Result<(), DbrError> {
+        let table = self.lookup_table(table_id)?;
+        let field_id = *table.lookup_field(field.to_owned())?;
+        if let Some(table) = self.tables.get_mut(&table_id) {
+            table.version_field = Some(field_id);
+        }
+
+        Ok(())
+    }
+
+    pub fn lookup_primary_key(&self, table_id: TableId) -> Result<&Field, DbrError> {
+        let table = self.lookup_table(table_id)?;
+        let primary_key = table
+            .primary_key()
+            .ok_or(DbrError::Unimplemented("missing primary key".to_owned()))?;
+        self.lookup_field(primary_key)
+    }
+
+    /// Option of an enum field, by the value stored in the record.
+    pub fn lookup_enum(&self, field_id: FieldId, value: Enum) -> Result<&EnumOption, DbrError> {
+        self.enums
+            .get(&field_id)
+            .and_then(|options| options.iter().find(|option| option.id == value.id()))
+            .ok_or(
+                MetadataError::MissingEnum {
+                    field: field_id,
+                    option: EnumIdentifier::Id(value.id()),
+                }
+                .into(),
+            )
+    }
+
+    /// Option of an enum field by its handle, e.g. to set it.
+    pub fn lookup_enum_by_handle(
+        &self,
+        field_id: FieldId,
+        handle: &str,
+    ) -> Result<&EnumOption, DbrError> {
+        self.enums
+            .get(&field_id)
+            .and_then(|options| options.iter().find(|option| option.handle == handle))
+            .ok_or(
+                MetadataError::MissingEnum {
+                    field: field_id,
+                    option: EnumIdentifier::Handle(handle.to_owned()),
+                }
+                .into(),
+            )
+    }
+
     pub fn lookup_relation(&self, relation_id: RelationId) -> Result<&Relation, DbrError> {
         self.relations
             .get(&relation_id)
@@ -312,6 +564,29 @@ impl Metadata {
     }
 }
 
+/// Serialized form of the metadata, the same rows that are in the dbr database.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetadataSnapshot {
+    pub schemas: Vec<SchemaInfo>,
+    pub tables: Vec<TableInfo>,
+    pub fields: Vec<Field>,
+    pub relations: Vec<Relation>,
+    #[serde(default)]
+    pub enums: Vec<EnumOption>,
+}
+
+impl MetadataSnapshot {
+    pub fn into_metadata(self) -> Result<Metadata, DbrError> {
+        Metadata::build(
+            self.schemas,
+            self.tables,
+            self.fields,
+            self.relations,
+            self.enums,
+        )
+    }
+}
+
 /*
 MySQL [dbr]> select * from dbr_schemas limit 1;
 +-----------+--------+---------------+
@@ -320,7 +595,7 @@ MySQL [dbr]> select * from dbr_schemas limit 1;
 |         1 | config | Configuration |
 +-----------+--------+---------------+
 */
-#[derive(sqlx::FromRow, Debug, Clone)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
 pub struct SchemaInfo {
     #[sqlx(rename = "schema_id")]
     pub id: SchemaId,
@@ -379,7 +654,7 @@ MySQL [dbr]> select * from dbr_tables limit 1;
 |        1 |         1 | attribute_config_map | NULL         |           0 |
 +----------+-----------+----------------------+--------------+-------------+
 */
-#[derive(sqlx::FromRow, Debug, Clone)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
 pub struct TableInfo {
     #[sqlx(rename = "table_id")]
     pub id: TableId,
@@ -387,12 +662,19 @@ pub struct TableInfo {
     pub name: String,
 }
 
+/// Numeric field picked up as a table's version column when there is one, see `Table::version_field`
+pub const VERSION_FIELD: &'static str = "version";
+
 #[derive(Deref, Debug, Clone)]
 pub struct Table {
     #[deref]
     pub info: TableInfo,
 
     pub primary_key: Option<FieldId>,
+
+    /// Incremented by every `UPDATE` and checked against the version the record was loaded at,
+    /// so writes over someone else's changes fail with `DbrError::Conflict`.
+    pub version_field: Option<FieldId>,
     pub fields: HashMap<String, FieldId>,
     pub relations: HashMap<String, Vec<RelationId>>,
 }
@@ -442,6 +724,10 @@ impl Table {
     pub fn primary_key(&self) -> Option<FieldId> {
         self.primary_key
     }
+
+    pub fn version_field(&self) -> Option<FieldId> {
+        self.version_field
+    }
 }
 /*
         BigInt    => { id => 1, numeric => 1, bits => 64},
@@ -471,6 +757,152 @@ impl Table {
         varbinary => { id => 21 },
 */
 
+/// Type of a field, decoded from `dbr_fields.data_type`
+///
+/// The ids match the type list of the original DBR.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DataType {
+    BigInt,
+    Int,
+    MediumInt,
+    SmallInt,
+    TinyInt,
+    Bool,
+    Float,
+    Double,
+    VarChar,
+    Char,
+    Text,
+    MediumText,
+    Blob,
+    LongBlob,
+    MediumBlob,
+    TinyBlob,
+    Enum,
+    Decimal,
+    DateTime,
+    Binary,
+    VarBinary,
+}
+
+impl DataType {
+    pub fn from_id(id: u32) -> Option<Self> {
+        let data_type = match id {
+            1 => Self::BigInt,
+            2 => Self::Int,
+            3 => Self::MediumInt,
+            4 => Self::SmallInt,
+            5 => Self::TinyInt,
+            6 => Self::Bool,
+            7 => Self::Float,
+            8 => Self::Double,
+            9 => Self::VarChar,
+            10 => Self::Char,
+            11 => Self::Text,
+            12 => Self::MediumText,
+            13 => Self::Blob,
+            14 => Self::LongBlob,
+            15 => Self::MediumBlob,
+            16 => Self::TinyBlob,
+            17 => Self::Enum,
+            18 => Self::Decimal,
+            19 => Self::DateTime,
+            20 => Self::Binary,
+            21 => Self::VarBinary,
+            _ => return None,
+        };
+
+        Some(data_type)
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::BigInt => 1,
+            Self::Int => 2,
+            Self::MediumInt => 3,
+            Self::SmallInt => 4,
+            Self::TinyInt => 5,
+            Self::Bool => 6,
+            Self::Float => 7,
+            Self::Double => 8,
+            Self::VarChar => 9,
+            Self::Char => 10,
+            Self::Text => 11,
+            Self::MediumText => 12,
+            Self::Blob => 13,
+            Self::LongBlob => 14,
+            Self::MediumBlob => 15,
+            Self::TinyBlob => 16,
+            Self::Enum => 17,
+            Self::Decimal => 18,
+            Self::DateTime => 19,
+            Self::Binary => 20,
+            Self::VarBinary => 21,
+        }
+    }
+
+    /// Type from its name in a column definition, e.g. `int` or `character varying` from Postgres.
+    pub fn from_type_name(name: &str) -> Option<Self> {
+        let data_type = match name.to_lowercase().as_str() {
+            "bigint" | "int8" | "bigserial" => Self::BigInt,
+            "int" | "integer" | "int4" | "serial" => Self::Int,
+            "mediumint" => Self::MediumInt,
+            "smallint" | "int2" | "smallserial" => Self::SmallInt,
+            "tinyint" => Self::TinyInt,
+            "bool" | "boolean" => Self::Bool,
+            "float" | "real" | "float4" => Self::Float,
+            "double" | "double precision" | "float8" => Self::Double,
+            "varchar" | "character varying" => Self::VarChar,
+            "char" | "character" => Self::Char,
+            "text" => Self::Text,
+            "mediumtext" => Self::MediumText,
+            "blob" => Self::Blob,
+            "longblob" => Self::LongBlob,
+            "mediumblob" => Self::MediumBlob,
+            "tinyblob" => Self::TinyBlob,
+            "enum" => Self::Enum,
+            "decimal" | "numeric" => Self::Decimal,
+            "datetime"
+            | "timestamp"
+            | "timestamp without time zone"
+            | "timestamp with time zone" => Self::DateTime,
+            "binary" => Self::Binary,
+            "varbinary" | "bytea" => Self::VarBinary,
+            _ => return None,
+        };
+
+        Some(data_type)
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            Self::BigInt
+            | Self::Int
+            | Self::MediumInt
+            | Self::SmallInt
+            | Self::TinyInt
+            | Self::Bool
+            | Self::Float
+            | Self::Double
+            | Self::Decimal => true,
+            _ => false,
+        }
+    }
+
+    /// Width of the integer types, `None` for anything else.
+    pub fn bits(&self) -> Option<u32> {
+        match self {
+            Self::BigInt => Some(64),
+            Self::Int => Some(32),
+            Self::MediumInt => Some(24),
+            Self::SmallInt => Some(16),
+            Self::TinyInt => Some(8),
+            Self::Bool => Some(1),
+            _ => None,
+        }
+    }
+}
+
 /*
 MySQL [dbr]> select * from dbr_fields limit 1;
 +----------+----------+------+-----------+-------------+-----------+-----------+--------------+---------+------------+----------+-------+-------------+
@@ -479,7 +911,7 @@ MySQL [dbr]> select * from dbr_fields limit 1;
 |        1 |        1 | id   |         2 |           0 |         0 |        10 | NULL         |       1 |       NULL |     NULL | NULL  | NULL        |
 +----------+----------+------+-----------+-------------+-----------+-----------+--------------+---------+------------+----------+-------+-------------+
  */
-#[derive(sqlx::FromRow, Debug, Clone)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
 pub struct Field {
     #[sqlx(rename = "field_id")]
     pub id: FieldId,
@@ -492,14 +924,46 @@ pub struct Field {
     #[sqlx(rename = "is_pkey")]
     pub is_primary_key: bool,
     pub trans_id: Option<u32>,
-    //default_value:
+
+    /// Pattern values have to match before they are written, see `validation`
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[sqlx(rename = "default_val")]
+    #[serde(default)]
+    pub default_value: Option<String>,
 }
 
 impl Field {
+    /// `None` if the id isn't one we know about.
+    pub fn kind(&self) -> Option<DataType> {
+        DataType::from_id(self.data_type)
+    }
+
+    pub fn translator(&self) -> Option<Translator> {
+        self.trans_id.and_then(Translator::from_id)
+    }
+
+    /// Compile `regex` for `Metadata::patterns`, an invalid one is logged and left out of validation.
+    pub fn compile_pattern(&self) -> Option<Regex> {
+        let regex = self.regex.as_deref().filter(|regex| !regex.is_empty());
+        regex.and_then(|regex| match Regex::new(regex) {
+            Ok(pattern) => Some(pattern),
+            Err(err) => {
+                tracing::warn!(
+                    field = self.name.as_str(),
+                    regex,
+                    error = %err,
+                    "skipping invalid field regex"
+                );
+                None
+            }
+        })
+    }
+
     pub async fn fetch_all<'c, E: Executor<'c, Database = MySql>>(
         executor: E,
     ) -> Result<Vec<Self>, DbrError> {
-        sqlx::query_as(r"SELECT field_id, table_id, name, data_type, is_nullable, is_signed, max_value, is_pkey, trans_id FROM dbr_fields")
+        sqlx::query_as(r"SELECT field_id, table_id, name, data_type, is_nullable, is_signed, max_value, is_pkey, trans_id, regex, default_val FROM dbr_fields")
             .fetch_all(executor)
             .await
             .map_err(|err| DbrError::from(err))
@@ -522,7 +986,7 @@ pub enum RelationType {
 //ManyToOne,
 }
 
-#[derive(sqlx::FromRow, Debug, Clone)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
 pub struct Relation {
     #[sqlx(rename = "relationship_id")]
     pub id: RelationId,
@@ -543,3 +1007,90 @@ impl Relation {
             .map_err(|err| DbrError::from(err))
     }
 }
+
+/*
+MySQL [dbr]> select * from enum limit 1;
++---------+--------+--------+-------------+
+| enum_id | handle | name   | override_id |
++---------+--------+--------+-------------+
+|       1 | active | Active |        NULL |
++---------+--------+--------+-------------+
+
+MySQL [dbr]> select * from enum_map limit 1;
++--------+----------+---------+---------+
+| row_id | field_id | enum_id | sortval |
++--------+----------+---------+---------+
+|      1 |       42 |       1 |       1 |
++--------+----------+---------+---------+
+*/
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct EnumOption {
+    /// Value stored in the record, the override id if there is one.
+    #[sqlx(rename = "enum_id")]
+    pub id: u32,
+    pub field_id: FieldId,
+    pub handle: String,
+    pub name: String,
+    #[sqlx(rename = "sortval")]
+    pub sort_value: i32,
+}
+
+/// SQLSTATE MySQL reports for a table that doesn't exist.
+const NO_SUCH_TABLE: &str = "42S02";
+
+impl EnumOption {
+    /// Options of every enum field, none if the dbr database doesn't have the `enum` and `enum_map` tables.
+    pub async fn fetch_all<'c, E: Executor<'c, Database = MySql>>(
+        executor: E,
+    ) -> Result<Vec<Self>, DbrError> {
+        let options = sqlx::query_as(r"SELECT COALESCE(e.override_id, e.enum_id) AS enum_id, m.field_id, e.handle, e.name, COALESCE(m.sortval, 0) AS sortval FROM enum_map m JOIN `enum` e ON e.enum_id = m.enum_id")
+            .fetch_all(executor)
+            .await;
+
+        match options {
+            Ok(options) => Ok(options),
+            Err(sqlx::Error::Database(err)) if err.code().as_deref() == Some(NO_SUCH_TABLE) => {
+                Ok(Vec::new())
+            }
+            Err(err) => Err(DbrError::from(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Metadata {
+    /// `ops.song` with a field of a few different kinds, for the tests of anything reading metadata.
+    pub(crate) fn for_tests() -> Self {
+        let snapshot = serde_json::json!({
+            "schemas": [{ "id": 1, "name": "ops", "display_name": "Ops" }],
+            "tables": [{ "id": 1, "schema_id": 1, "name": "song" }],
+            "fields": [
+                {
+                    "id": 1, "table_id": 1, "name": "id", "data_type": 2, "is_nullable": false,
+                    "is_signed": false, "max_value": 10, "is_primary_key": true, "trans_id": null
+                },
+                {
+                    "id": 2, "table_id": 1, "name": "name", "data_type": 9, "is_nullable": true,
+                    "is_signed": false, "max_value": 16, "is_primary_key": false, "trans_id": null,
+                    "regex": "^[A-Za-z ]+$"
+                },
+                {
+                    "id": 3, "table_id": 1, "name": "plays", "data_type": 4, "is_nullable": false,
+                    "is_signed": true, "max_value": 5, "is_primary_key": false, "trans_id": null,
+                    "default_value": "0"
+                },
+                {
+                    "id": 4, "table_id": 1, "name": "version", "data_type": 2, "is_nullable": false,
+                    "is_signed": false, "max_value": 10, "is_primary_key": false, "trans_id": null,
+                    "default_value": "0"
+                }
+            ],
+            "relations": []
+        });
+
+        serde_json::from_value::<MetadataSnapshot>(snapshot)
+            .unwrap()
+            .into_metadata()
+            .unwrap()
+    }
+}
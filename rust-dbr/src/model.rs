@@ -1,9 +1,14 @@
 use std::{
+    any::Any,
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
     ops::{Deref, DerefMut},
     sync::{Arc, Mutex},
 };
 
-use crate::prelude::*;
+use sqlx::any::AnyRow;
+
+use crate::{filter::BindValue, prelude::*};
 
 /// Implemented on structures that are seen as the working data of the database.
 ///
@@ -18,14 +23,13 @@ where
         let locked_record = self.data().lock().map_err(|_| DbrError::PoisonError)?;
         Ok(locked_record.clone().data)
     }
-    fn apply_partial<P: PartialModel<T>>(&self, partial: P) -> Result<(), DbrError> {
+    fn apply_partial(&self, partial: T::PartialModel) -> Result<(), DbrError> {
         if let Some(_id) = partial.id() {
             return Err(DbrError::CannotSetID);
         }
 
         let mut data = self.data().lock().map_err(|_| DbrError::PoisonError)?;
-        partial.apply(&mut *data)?;
-        Ok(())
+        data.apply(partial)
     }
     fn set_snapshot(&self, snapshot: T) -> Result<(), DbrError> {
         let mut data = self.data().lock().map_err(|_| DbrError::PoisonError)?;
@@ -43,6 +47,75 @@ where
     where
         R: Deref<Target = T> + DerefMut;
     fn id(&self) -> Option<<T as DbrTable>::Id>;
+
+    /// Names of the fields that have been set.
+    fn fields(&self) -> Vec<&'static str>;
+
+    /// Partial with the given fields copied from `record`, unknown names are ignored.
+    fn from_record(record: &T, fields: &[&str]) -> Self;
+
+    /// Names of the fields that have been set along with their values bound in the same order.
+    fn into_arguments(self) -> (Vec<&'static str>, BindValue);
+
+    /// Fields that have been set with their values, for `Metadata::validate`
+    fn field_values(&self) -> Vec<(&'static str, FieldValue)>;
+
+    /// Partial setting the version field `field` to `version`, `None` if `T` has no integer field of that name.
+    fn version(field: &str, version: u64) -> Option<Self>
+    where
+        Self: Sized;
+}
+
+/// Integer types a version field can be, see `Table::version_field`
+pub trait FromVersion: Sized {
+    fn from_version(version: u64) -> Option<Self>;
+}
+
+macro_rules! from_version {
+    ($($ty:ty),*) => {
+        $(
+            impl FromVersion for $ty {
+                fn from_version(version: u64) -> Option<Self> {
+                    version.try_into().ok()
+                }
+            }
+        )*
+    };
+}
+
+from_version!(i16, i32, i64, u16, u32, u64);
+
+impl<T: FromVersion> FromVersion for Option<T> {
+    fn from_version(version: u64) -> Option<Self> {
+        T::from_version(version).map(Some)
+    }
+}
+
+// Same trick as `validation::Probe`, `(&VersionProbe::<T>(PhantomData)).from_version(..)` is `None`
+// for field types that can't hold a version instead of failing to compile.
+#[doc(hidden)]
+pub struct VersionProbe<T>(pub PhantomData<T>);
+
+#[doc(hidden)]
+pub trait ViaFromVersion<T> {
+    fn from_version(&self, version: u64) -> Option<T>;
+}
+
+impl<T: FromVersion> ViaFromVersion<T> for VersionProbe<T> {
+    fn from_version(&self, version: u64) -> Option<T> {
+        T::from_version(version)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaNoVersion<T> {
+    fn from_version(&self, version: u64) -> Option<T>;
+}
+
+impl<T> ViaNoVersion<T> for &VersionProbe<T> {
+    fn from_version(&self, _version: u64) -> Option<T> {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +125,9 @@ where
 {
     id: <T as DbrTable>::Id,
     data: Arc<Mutex<RecordMetadata<T>>>,
+
+    /// Records a `prefetch` loaded through this one, shared by the clones of the handle.
+    prefetched: Arc<Mutex<Vec<Arc<dyn Any + Send + Sync>>>>,
 }
 
 impl<T> Active<T>
@@ -59,8 +135,553 @@ where
     T: DbrTable,
 {
     pub fn from_arc(id: <T as DbrTable>::Id, data: Arc<Mutex<RecordMetadata<T>>>) -> Self {
-        Self { id, data }
+        Self {
+            id,
+            data,
+            prefetched: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Keep records prefetched through this one alive for as long as this handle (or a clone of it) is.
+    ///
+    /// The record itself only holds weak handles to them, see `RecordMetadata::relations`.
+    pub fn hold<R: Any + Send + Sync>(&self, related: R) -> Result<(), DbrError> {
+        let mut prefetched = self.prefetched.lock().map_err(|_| DbrError::PoisonError)?;
+        prefetched.push(Arc::new(related));
+        Ok(())
+    }
+
+    /// Insert a new record and register it in the record cache.
+    ///
+    /// Every non-nullable field aside from the primary key and those with a default has to be set on the partial,
+    /// the id is picked up from the auto increment (or `RETURNING` on Postgres) unless the partial sets it.
+    /// The values are validated against the field metadata before anything is inserted.
+    pub async fn create(context: &Context, partial: T::PartialModel) -> Result<Self, DbrError>
+    where
+        T::Id: TryFrom<i64>,
+    {
+        let instance = context.instance_for::<T>()?;
+        let table = context.metadata.lookup_dbr_table::<T>()?;
+        let primary_key = context.metadata.lookup_primary_key(table.id)?;
+
+        let partial_id = partial.id();
+        context.metadata.validate::<T>(&partial.field_values())?;
+        let (fields, arguments) = partial.into_arguments();
+        check_required_fields(context, table, &fields)?;
+
+        let dialect = instance.dialect();
+        let mut query_str = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            dialect.qualified_table(instance.info.database_name(), T::table_name()),
+            fields
+                .iter()
+                .map(|field| dialect.quote_identifier(field))
+                .collect::<Vec<_>>()
+                .join(", "),
+            vec!["?"; fields.len()].join(", "),
+        );
+
+        let inserted_id = if partial_id.is_none() && dialect.needs_returning() {
+            query_str += &format!(" RETURNING {}", dialect.quote_identifier(&primary_key.name));
+            let rows = context.fetch_all(&instance, &query_str, arguments).await?;
+            let row = rows.first().ok_or(DbrError::RecordNotFetched)?;
+            Some(returned_id(row, primary_key)?)
+        } else {
+            let result = context.execute(&instance, &query_str, arguments).await?;
+            result.last_insert_id()
+        };
+
+        let id = match partial_id {
+            Some(id) => id,
+            None => {
+                let inserted_id = inserted_id.ok_or_else(|| {
+                    DbrError::Unimplemented(format!(
+                        "{} backend didn't report the inserted id",
+                        instance.info.module()
+                    ))
+                })?;
+                T::Id::try_from(inserted_id).map_err(|_| DbrError::InvalidInsertId(inserted_id))?
+            }
+        };
+
+        // Read the row back so we pick up anything the database filled in for us.
+        let select = Select::filtered_on(
+            context,
+            table.id,
+            primary_key.name.clone(),
+            FilterOp::Eq,
+            FilterValue::scalar(id),
+        )?;
+
+        select
+            .fetch_active::<T>(context)
+            .await?
+            .pop()
+            .ok_or(DbrError::RecordNotFetched)
+    }
+
+    /// Write the partial to the database right away and apply it to the cached record.
+    pub async fn update(
+        &self,
+        context: &Context,
+        partial: T::PartialModel,
+    ) -> Result<(), DbrError> {
+        if let Some(_id) = partial.id() {
+            return Err(DbrError::CannotSetID);
+        }
+
+        context.metadata.validate::<T>(&partial.field_values())?;
+        self.write_fields(context, partial.clone()).await?;
+        context.apply_partial(self, partial)
+    }
+
+    /// Apply the partial to the cached record without writing it, see `save`
+    ///
+    /// The values are still validated up front. Staged changes are visible to anyone
+    /// holding onto the same record, transaction or not.
+    pub fn stage(&self, context: &Context, partial: T::PartialModel) -> Result<(), DbrError> {
+        if let Some(_id) = partial.id() {
+            return Err(DbrError::CannotSetID);
+        }
+
+        context.metadata.validate::<T>(&partial.field_values())?;
+        let mut data = self.data.lock().map_err(|_| DbrError::PoisonError)?;
+        data.stage(partial)
+    }
+
+    /// Whether there are staged changes that haven't been saved.
+    pub fn is_dirty(&self) -> Result<bool, DbrError> {
+        let data = self.data.lock().map_err(|_| DbrError::PoisonError)?;
+        Ok(data.is_dirty())
+    }
+
+    /// The staged changes that haven't been saved.
+    pub fn changes(&self) -> Result<T::PartialModel, DbrError> {
+        let data = self.data.lock().map_err(|_| DbrError::PoisonError)?;
+        Ok(data.changes())
+    }
+
+    /// Throw away the staged changes, going back to the record as it was last loaded.
+    pub fn discard(&self) -> Result<(), DbrError> {
+        let mut data = self.data.lock().map_err(|_| DbrError::PoisonError)?;
+        data.discard();
+        Ok(())
+    }
+
+    /// Write the staged changes in a single `UPDATE` of just the dirty fields.
+    ///
+    /// Inside of a transaction a record handed out from outside of it stays dirty until the commit.
+    pub async fn save(&self, context: &Context) -> Result<(), DbrError> {
+        let changes = self.changes()?;
+        if !self.write_fields(context, changes.clone()).await? {
+            return Ok(());
+        }
+
+        context.record_write(self, move |data| data.saved(changes.clone()))
+    }
+
+    /// `UPDATE` the fields set on the partial, returns false if there weren't any.
+    ///
+    /// On tables with a version field the row only gets updated if it's still at the version
+    /// we know about, otherwise the fresh row is loaded and handed back in a `DbrError::Conflict`.
+    async fn write_fields(
+        &self,
+        context: &Context,
+        partial: T::PartialModel,
+    ) -> Result<bool, DbrError> {
+        use sqlx::Arguments;
+
+        let instance = context.instance_for::<T>()?;
+        let table = context.metadata.lookup_dbr_table::<T>()?;
+        let primary_key = context.metadata.lookup_primary_key(table.id)?;
+
+        let (fields, mut arguments) = partial.into_arguments();
+        if fields.len() == 0 {
+            return Ok(false);
+        }
+
+        let version_field = match table.version_field() {
+            Some(field_id) => Some(context.metadata.lookup_field(field_id)?),
+            None => None,
+        };
+
+        // Setting the version explicitly opts out of the check.
+        let version = match version_field {
+            Some(field) if !fields.contains(&field.name.as_str()) => self
+                .version(context, &field.name)?
+                .map(|version| (field, version)),
+            _ => None,
+        };
+
+        arguments.add(self.id.clone());
+        if let Some((_, version)) = version {
+            arguments.add(version as i64);
+        }
+
+        let dialect = instance.dialect();
+        let query_str = update_sql(
+            dialect,
+            &dialect.qualified_table(instance.info.database_name(), T::table_name()),
+            &fields,
+            &primary_key.name,
+            version.map(|(field, _)| field.name.as_str()),
+        );
+        let result = context.execute(&instance, &query_str, arguments).await?;
+
+        if let Some((field, version)) = version {
+            if result.rows_affected() == 0 {
+                let select = Select::filtered_on(
+                    context,
+                    table.id,
+                    primary_key.name.clone(),
+                    FilterOp::Eq,
+                    FilterValue::scalar(self.id.clone()),
+                )?;
+
+                let current = select
+                    .fetch_active::<T>(context)
+                    .await?
+                    .pop()
+                    .ok_or(DbrError::RecordNotFetched)?;
+
+                return Err(DbrError::Conflict {
+                    table: table.name.clone(),
+                    current: Box::new(current),
+                });
+            }
+
+            // The transaction's copy sees the new version right away, so a second write inside of it
+            // doesn't conflict with the first.
+            let field = field.name.clone();
+            context.record_write(self, move |data| data.written_at(&field, version + 1))?;
+        }
+
+        Ok(true)
     }
+
+    /// Version of the row we expect to be in the database, see `RecordMetadata::update_time`
+    ///
+    /// `None` if the version field isn't part of `T` or isn't an integer.
+    fn version(&self, context: &Context, field: &str) -> Result<Option<u64>, DbrError> {
+        let data = context.current_record(self)?;
+        let data = data.lock().map_err(|_| DbrError::PoisonError)?;
+        if data.update_time > 0 {
+            return Ok(Some(data.update_time));
+        }
+
+        let loaded = data.loaded.as_ref().unwrap_or(&data.data);
+        let values = T::PartialModel::from_record(loaded, &[field]).field_values();
+        let version = values.into_iter().find_map(|(_, value)| match value {
+            FieldValue::Int(version) => u64::try_from(version).ok(),
+            FieldValue::UInt(version) => Some(version),
+            _ => None,
+        });
+
+        Ok(version)
+    }
+
+    /// Read the record from the database again, see `reload_all`
+    pub async fn reload(&self, context: &Context) -> Result<(), DbrError> {
+        Self::reload_all(context, std::slice::from_ref(self)).await
+    }
+
+    /// Read the records from the database again in one query.
+    ///
+    /// The records are updated in place, so everything holding onto them sees the new values.
+    /// Inside of a transaction that's the transaction's own copies right away, the rest once it commits,
+    /// see `Context::record_write`. Staged changes are kept on top of them. Records that have been deleted are dropped from
+    /// the record cache and reported as `RecordNotFetched` once the rest have been reloaded.
+    pub async fn reload_all(context: &Context, records: &[Self]) -> Result<(), DbrError> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let table = context.metadata.lookup_dbr_table::<T>()?;
+        let primary_key = context.metadata.lookup_primary_key(table.id)?;
+        let ids: BTreeSet<T::Id> = records.iter().map(|record| record.id.clone()).collect();
+
+        let select = Select::filtered_on(
+            context,
+            table.id,
+            primary_key.name.clone(),
+            FilterOp::In,
+            FilterValue::list(ids),
+        )?;
+
+        let (instance, rows) = select.fetch_records::<T>(context).await?;
+        let rows: BTreeMap<T::Id, T> = rows.into_iter().map(|row| (row.id(), row)).collect();
+
+        let mut deleted = false;
+        for record in records {
+            match rows.get(&record.id) {
+                Some(row) => {
+                    let row = row.clone();
+                    context.record_write(record, move |data| data.reload(row.clone()))?;
+                }
+                None => {
+                    deleted = true;
+                    context.remove_record::<T>(&instance, record.id.clone())?;
+                }
+            }
+        }
+
+        if deleted {
+            return Err(DbrError::RecordNotFetched);
+        }
+
+        Ok(())
+    }
+
+    /// Delete the record by its primary key and drop it from the record cache.
+    ///
+    /// Returns the number of rows deleted.
+    pub async fn delete(&self, context: &Context) -> Result<u64, DbrError> {
+        use sqlx::Arguments;
+
+        let instance = context.instance_for::<T>()?;
+        let table = context.metadata.lookup_dbr_table::<T>()?;
+        let primary_key = context.metadata.lookup_primary_key(table.id)?;
+
+        let mut arguments = BindValue::default();
+        arguments.add(self.id.clone());
+
+        let dialect = instance.dialect();
+        let query_str = format!(
+            "DELETE FROM {} WHERE {} = ?",
+            dialect.qualified_table(instance.info.database_name(), T::table_name()),
+            dialect.quote_identifier(&primary_key.name)
+        );
+        let result = context.execute(&instance, &query_str, arguments).await?;
+
+        context.remove_record::<T>(&instance, self.id.clone())?;
+        Ok(result.rows_affected())
+    }
+
+    /// Insert several records, see `create`.
+    ///
+    /// All of them are validated up front, then inserted with one multi-row `INSERT` for each set of
+    /// fields the partials have. Unless the context already is in a transaction the inserts get one
+    /// of their own, so either every record is created or none of them are.
+    ///
+    /// Without `RETURNING` the new ids are worked out from the one the driver reports, which
+    /// relies on MySQL handing out consecutive ids to a single statement. That doesn't hold with
+    /// `innodb_autoinc_lock_mode = 2` while other inserts are running, set the ids on the partials there.
+    pub async fn insert_many(
+        context: &Context,
+        partials: Vec<T::PartialModel>,
+    ) -> Result<Vec<Self>, DbrError>
+    where
+        T::Id: TryFrom<i64>,
+    {
+        for partial in &partials {
+            context.metadata.validate::<T>(&partial.field_values())?;
+        }
+
+        if partials.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if context.in_transaction() {
+            return Self::insert_batches(context, partials).await;
+        }
+
+        let transaction = context.begin_transaction();
+        match Self::insert_batches(&transaction, partials).await {
+            Ok(records) => {
+                transaction.commit().await?;
+                Ok(records)
+            }
+            Err(err) => {
+                transaction.rollback().await?;
+                Err(err)
+            }
+        }
+    }
+
+    async fn insert_batches(
+        context: &Context,
+        partials: Vec<T::PartialModel>,
+    ) -> Result<Vec<Self>, DbrError>
+    where
+        T::Id: TryFrom<i64>,
+    {
+        let instance = context.instance_for::<T>()?;
+        let table = context.metadata.lookup_dbr_table::<T>()?;
+        let primary_key = context.metadata.lookup_primary_key(table.id)?;
+        let dialect = instance.dialect();
+
+        // Partials setting the same fields can share a statement, the position of each partial
+        // is kept so the records come back in the order they were given.
+        let mut batches: Vec<(Vec<&'static str>, Vec<usize>, BindValue)> = Vec::new();
+        let mut ids: Vec<Option<T::Id>> = Vec::new();
+        for (position, partial) in partials.into_iter().enumerate() {
+            ids.push(partial.id());
+            let (fields, arguments) = partial.into_arguments();
+            check_required_fields(context, table, &fields)?;
+
+            match batches
+                .iter_mut()
+                .find(|(batch_fields, _, _)| *batch_fields == fields)
+            {
+                Some((_, positions, batch_arguments)) => {
+                    positions.push(position);
+                    batch_arguments.extend(arguments);
+                }
+                None => batches.push((fields, vec![position], arguments)),
+            }
+        }
+
+        for (fields, positions, arguments) in batches {
+            let row = format!("({})", vec!["?"; fields.len()].join(", "));
+            let mut query_str = format!(
+                "INSERT INTO {} ({}) VALUES {}",
+                dialect.qualified_table(instance.info.database_name(), T::table_name()),
+                fields
+                    .iter()
+                    .map(|field| dialect.quote_identifier(field))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                vec![row; positions.len()].join(", "),
+            );
+
+            // Either every partial in the batch set the id or none of them did.
+            if ids[positions[0]].is_some() {
+                context.execute(&instance, &query_str, arguments).await?;
+                continue;
+            }
+
+            let inserted_ids = if dialect.needs_returning() {
+                query_str += &format!(" RETURNING {}", dialect.quote_identifier(&primary_key.name));
+                let rows = context.fetch_all(&instance, &query_str, arguments).await?;
+                rows.iter()
+                    .map(|row| returned_id(row, primary_key))
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                let result = context.execute(&instance, &query_str, arguments).await?;
+                let reported = result.last_insert_id().ok_or_else(|| {
+                    DbrError::Unimplemented(format!(
+                        "{} backend didn't report the inserted id",
+                        instance.info.module()
+                    ))
+                })?;
+
+                let first = dialect.first_inserted_id(reported, positions.len());
+                (first..first + positions.len() as i64).collect()
+            };
+
+            if inserted_ids.len() != positions.len() {
+                return Err(DbrError::RecordNotFetched);
+            }
+
+            for (position, inserted_id) in positions.into_iter().zip(inserted_ids) {
+                let id = T::Id::try_from(inserted_id)
+                    .map_err(|_| DbrError::InvalidInsertId(inserted_id))?;
+                ids[position] = Some(id);
+            }
+        }
+
+        let ids: Vec<T::Id> = ids.into_iter().flatten().collect();
+
+        // Read the rows back so we pick up anything the database filled in for us.
+        let select = Select::filtered_on(
+            context,
+            table.id,
+            primary_key.name.clone(),
+            FilterOp::In,
+            FilterValue::list(ids.iter().cloned().collect::<BTreeSet<_>>()),
+        )?;
+
+        let mut found: BTreeMap<T::Id, Self> = select
+            .fetch_active::<T>(context)
+            .await?
+            .into_iter()
+            .map(|record| (record.id.clone(), record))
+            .collect();
+
+        ids.iter()
+            .map(|id| found.remove(id).ok_or(DbrError::RecordNotFetched))
+            .collect()
+    }
+}
+
+/// Id of an inserted row from its `RETURNING` column.
+///
+/// Postgres won't decode an `int4` as an `i64`, so it's read as the type the primary key has
+/// in the generated structs first, see `codegen::rust_type`
+fn returned_id(row: &AnyRow, primary_key: &Field) -> Result<i64, DbrError> {
+    use sqlx::Row;
+
+    let id = match primary_key.kind() {
+        Some(DataType::Int) if primary_key.is_signed => row.try_get::<i32, _>(0)?.into(),
+        Some(DataType::MediumInt) => row.try_get::<i32, _>(0)?.into(),
+        Some(DataType::SmallInt) if primary_key.is_signed => row.try_get::<i16, _>(0)?.into(),
+        Some(DataType::SmallInt) => row.try_get::<i32, _>(0)?.into(),
+        Some(DataType::TinyInt) => row.try_get::<i16, _>(0)?.into(),
+        _ => row.try_get::<i64, _>(0)?,
+    };
+
+    Ok(id)
+}
+
+/// Every non-nullable field aside from the primary key and those with a default has to be in `fields`
+fn check_required_fields(
+    context: &Context,
+    table: &Table,
+    fields: &[&str],
+) -> Result<(), DbrError> {
+    let mut missing = Vec::new();
+    for field_id in table.fields.values() {
+        let field = context.metadata.lookup_field(*field_id)?;
+        if !field.is_nullable
+            && !field.is_primary_key
+            && field.default_value.is_none()
+            && !fields.contains(&field.name.as_str())
+        {
+            missing.push(field.name.clone());
+        }
+    }
+
+    if missing.len() > 0 {
+        missing.sort();
+        return Err(DbrError::MissingFields {
+            table: table.name.clone(),
+            fields: missing,
+        });
+    }
+
+    Ok(())
+}
+
+/// `UPDATE` of `fields` by primary key, binds the values of the fields, the key and then the version.
+///
+/// `table` is already quoted, see `Dialect::qualified_table`
+///
+/// With a `version` field the row is only matched at the version we expect and the version is bumped.
+fn update_sql(
+    dialect: Dialect,
+    table: &str,
+    fields: &[&str],
+    primary_key: &str,
+    version: Option<&str>,
+) -> String {
+    let mut assignments = fields
+        .iter()
+        .map(|field| format!("{} = ?", dialect.quote_identifier(field)))
+        .collect::<Vec<_>>();
+    let mut predicate = format!("{} = ?", dialect.quote_identifier(primary_key));
+
+    if let Some(version) = version {
+        let version_column = dialect.quote_identifier(version);
+        assignments.push(format!("{} = {} + 1", version_column, version_column));
+        predicate += &format!(" AND {} = ?", version_column);
+    }
+
+    format!(
+        "UPDATE {} SET {} WHERE {}",
+        table,
+        assignments.join(", "),
+        predicate,
+    )
 }
 
 impl<T> ActiveModel<T> for Active<T>
@@ -74,3 +695,50 @@ where
         &self.data
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_check_and_bump_the_version() {
+        assert_eq!(
+            update_sql(
+                Dialect::MySql,
+                &Dialect::MySql.qualified_table("ops", "song"),
+                &["name", "plays"],
+                "id",
+                Some("version")
+            ),
+            "UPDATE `ops`.`song` SET `name` = ?, `plays` = ?, `version` = `version` + 1 WHERE `id` = ? AND `version` = ?"
+        );
+    }
+
+    #[test]
+    fn updates_without_a_version_only_match_the_key() {
+        assert_eq!(
+            update_sql(
+                Dialect::Postgres,
+                &Dialect::Postgres.qualified_table("ops", "song"),
+                &["name"],
+                "id",
+                None
+            ),
+            r#"UPDATE "song" SET "name" = ? WHERE "id" = ?"#
+        );
+    }
+
+    #[test]
+    fn versions_only_fit_integer_fields() {
+        assert_eq!((&VersionProbe::<i32>(PhantomData)).from_version(7), Some(7));
+        assert_eq!(
+            (&VersionProbe::<Option<u64>>(PhantomData)).from_version(7),
+            Some(Some(7))
+        );
+        assert_eq!(
+            (&VersionProbe::<i16>(PhantomData)).from_version(1 << 20),
+            None
+        );
+        assert_eq!((&VersionProbe::<String>(PhantomData)).from_version(7), None);
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::prelude::*;
+
+/// What kind of statement was run, going off of its first keyword.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatementKind {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Other,
+}
+
+impl StatementKind {
+    pub fn of(sql: &str) -> Self {
+        let keyword = sql
+            .trim_start()
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        match keyword.to_uppercase().as_str() {
+            "SELECT" => Self::Select,
+            "INSERT" => Self::Insert,
+            "UPDATE" => Self::Update,
+            "DELETE" => Self::Delete,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Everything we know about a statement once it has finished running.
+#[derive(Debug)]
+pub struct QueryEvent<'a> {
+    /// The statement as it was sent to the database.
+    pub sql: &'a str,
+    pub statement: StatementKind,
+    pub argument_count: usize,
+    pub instance_id: DbrInstanceId,
+    pub handle: &'a str,
+    pub duration: Duration,
+
+    /// Rows returned by a select or affected by anything else.
+    pub rows: u64,
+    pub error: Option<&'a DbrError>,
+}
+
+/// Hook for every statement run through a `Context`, see `Context::with_observer`
+///
+/// This is called inline after each statement so it should be quick, hand anything slow off elsewhere.
+pub trait QueryObserver: Send + Sync {
+    fn on_query(&self, event: &QueryEvent<'_>);
+}
+
+/// Times a statement and reports it to the observer and `tracing` once it is done.
+pub(crate) struct QueryTimer<'a> {
+    observer: Option<&'a Arc<dyn QueryObserver>>,
+    instance: &'a DbrInstance,
+    sql: &'a str,
+    argument_count: usize,
+    started: Instant,
+    span: tracing::Span,
+}
+
+impl<'a> QueryTimer<'a> {
+    /// `sql` is the finalized statement, `argument_count` is counted before finalizing
+    /// since the placeholders might not be `?` anymore.
+    pub fn start(
+        observer: Option<&'a Arc<dyn QueryObserver>>,
+        instance: &'a DbrInstance,
+        sql: &'a str,
+        argument_count: usize,
+    ) -> Self {
+        let span = tracing::debug_span!(
+            "dbr_query",
+            instance = instance.info.id().0,
+            handle = instance.info.schema().as_str(),
+            statement = ?StatementKind::of(sql),
+        );
+
+        Self {
+            observer,
+            instance,
+            sql,
+            argument_count,
+            started: Instant::now(),
+            span,
+        }
+    }
+
+    pub fn span(&self) -> tracing::Span {
+        self.span.clone()
+    }
+
+    pub fn finish<T>(self, result: &Result<T, DbrError>, rows: impl FnOnce(&T) -> u64) {
+        let duration = self.started.elapsed();
+        let (rows, error) = match result {
+            Ok(value) => (rows(value), None),
+            Err(err) => (0, Some(err)),
+        };
+
+        let _entered = self.span.enter();
+        match error {
+            Some(err) => tracing::warn!(
+                sql = self.sql,
+                arguments = self.argument_count,
+                duration_ms = duration.as_millis() as u64,
+                error = %err,
+                "query failed"
+            ),
+            None => tracing::debug!(
+                sql = self.sql,
+                arguments = self.argument_count,
+                duration_ms = duration.as_millis() as u64,
+                rows = rows,
+                "query finished"
+            ),
+        }
+
+        if let Some(observer) = self.observer {
+            observer.on_query(&QueryEvent {
+                sql: self.sql,
+                statement: StatementKind::of(self.sql),
+                argument_count: self.argument_count,
+                instance_id: self.instance.info.id(),
+                handle: self.instance.info.schema(),
+                duration,
+                rows,
+                error,
+            });
+        }
+    }
+}
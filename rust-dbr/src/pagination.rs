@@ -0,0 +1,248 @@
+use std::fmt::{self, Display};
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::any::AnyRow;
+
+use crate::{filter::BindValue, prelude::*};
+
+/// How datetime order keys are written in a cursor
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Position in a paged fetch, see `Select::fetch_page`
+///
+/// This is the value of every order key of the last record on the page, with ties broken by the primary key,
+/// so paging doesn't have to scan past everything before it like `OFFSET` does and keeps going
+/// even if that record has been deleted since.
+/// Treat it as opaque, it can be handed out and read back through its `Display` and `FromStr` impls.
+#[derive(Debug, Clone)]
+pub struct Cursor<T: DbrTable> {
+    values: Vec<CursorValue>,
+    table: PhantomData<fn() -> T>,
+}
+
+/// Value of one order key in a `Cursor`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CursorValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl<T: DbrTable> Cursor<T> {
+    /// Cursor pointing just past `row`, `order` being the keys from `ResolvedSelect::keyset_order`
+    pub fn from_row(
+        row: &AnyRow,
+        order: &[(Field, Option<OrderDirection>)],
+    ) -> Result<Self, DbrError> {
+        let values = order
+            .iter()
+            .map(|(field, _)| CursorValue::decode(row, field))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            values,
+            table: PhantomData,
+        })
+    }
+
+    pub fn values(&self) -> &[CursorValue] {
+        &self.values
+    }
+}
+
+impl<T: DbrTable> Display for Cursor<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values = serde_json::to_string(&self.values).map_err(|_| fmt::Error)?;
+        write!(f, "{}", values)
+    }
+}
+
+impl<T: DbrTable> FromStr for Cursor<T> {
+    type Err = DbrError;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        let values =
+            serde_json::from_str(cursor).map_err(|_| DbrError::InvalidCursor(cursor.to_owned()))?;
+        Ok(Self {
+            values,
+            table: PhantomData,
+        })
+    }
+}
+
+impl CursorValue {
+    /// Value of `field` in a row, decoded the same way as the subquery keys in `filter`
+    pub fn decode(row: &AnyRow, field: &Field) -> Result<Self, DbrError> {
+        use sqlx::Row;
+
+        macro_rules! decode {
+            ($ty:ty, $variant:ident) => {
+                row.try_get::<Option<$ty>, _>(field.name.as_str())?
+                    .map_or(Self::Null, |value| Self::$variant(value.into()))
+            };
+        }
+
+        let value = match field.kind() {
+            Some(DataType::BigInt) => decode!(i64, Int),
+            Some(DataType::Int) if field.is_signed => decode!(i32, Int),
+            Some(DataType::Int) => decode!(i64, Int),
+            Some(DataType::MediumInt) => decode!(i32, Int),
+            Some(DataType::SmallInt) if field.is_signed => decode!(i16, Int),
+            Some(DataType::SmallInt) => decode!(i32, Int),
+            Some(DataType::TinyInt) => decode!(i16, Int),
+            Some(DataType::Bool) => decode!(bool, Int),
+            Some(DataType::Float) => decode!(f32, Float),
+            Some(DataType::Double) => decode!(f64, Float),
+            Some(DataType::DateTime) => row
+                .try_get::<Option<NaiveDateTime>, _>(field.name.as_str())?
+                .map_or(Self::Null, |value| {
+                    Self::Text(value.format(DATETIME_FORMAT).to_string())
+                }),
+            Some(DataType::Decimal) => decode!(Decimal, Text),
+            Some(
+                DataType::Blob
+                | DataType::LongBlob
+                | DataType::MediumBlob
+                | DataType::TinyBlob
+                | DataType::Binary
+                | DataType::VarBinary,
+            ) => {
+                return Err(DbrError::Unimplemented(format!(
+                    "paging ordered by binary field {}",
+                    field.name
+                )))
+            }
+            Some(_) | None => decode!(String, Text),
+        };
+
+        Ok(value)
+    }
+
+    /// Bind the value as the type `field` is compared as, `Null` is never bound, see `ResolvedSelect::keyset_sql`
+    pub fn bind(&self, field: &Field, values: &mut BindValue) -> Result<(), DbrError> {
+        use sqlx::Arguments;
+
+        let invalid = || DbrError::InvalidCursor(format!("{:?} for {}", self, field.name));
+        match (self, field.kind()) {
+            (Self::Null, _) => {}
+            (Self::Int(value), Some(DataType::Bool)) => values.add(*value != 0),
+            (Self::Int(value), _) => values.add(*value),
+            (Self::Float(value), _) => values.add(*value),
+            (Self::Text(value), Some(DataType::DateTime)) => values
+                .add(NaiveDateTime::parse_from_str(value, DATETIME_FORMAT).map_err(|_| invalid())?),
+            (Self::Text(value), Some(DataType::Decimal)) => {
+                values.add(value.parse::<Decimal>().map_err(|_| invalid())?)
+            }
+            (Self::Text(value), _) => values.add(value.clone()),
+        }
+
+        Ok(())
+    }
+}
+
+/// One page of records and where to pick up for the next one, `None` once we've run out.
+#[derive(Debug, Clone)]
+pub struct Page<T: DbrTable> {
+    pub records: Vec<Active<T>>,
+    pub next: Option<Cursor<T>>,
+}
+
+impl Select {
+    /// Fetch up to `page_size` records that come after `after` in the order of this select.
+    ///
+    /// The order is always finished off with the primary key, so records that tie on the order keys
+    /// still come back in the same order on every page.
+    pub async fn fetch_page<T: DbrTable>(
+        mut self,
+        context: &Context,
+        page_size: i64,
+        after: Option<Cursor<T>>,
+    ) -> Result<Page<T>, DbrError> {
+        use sqlx::{Arguments, FromRow};
+
+        let mut limit = BindValue::default();
+        limit.add(page_size);
+        self.limit = Some(limit);
+        self.after = after.map(|cursor| cursor.values);
+
+        let mut resolved_select = self
+            .resolve(context)?
+            .run_external_subqueries(context)
+            .await?;
+        if resolved_select.is_unsatisfiable() {
+            return Ok(Page {
+                records: Vec::new(),
+                next: None,
+            });
+        }
+
+        let order = resolved_select.keyset_order()?;
+        resolved_select.order = order.clone();
+
+        let instance = resolved_select.primary_table.instance.clone();
+        let (sql, args) = resolved_select.as_sql()?;
+        let rows = context.fetch_all(&instance, &sql, args).await?;
+
+        let next = match rows.last() {
+            Some(last) if rows.len() as i64 >= page_size => Some(Cursor::from_row(last, &order)?),
+            _ => None,
+        };
+
+        let mut records = Vec::with_capacity(rows.len());
+        for row in &rows {
+            records.push(context.register_record(&instance, T::from_row(row)?)?);
+        }
+
+        Ok(Page { records, next })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_values_round_trip() {
+        let values = vec![
+            CursorValue::Text("Abbey Road".into()),
+            CursorValue::Null,
+            CursorValue::Int(-3),
+            CursorValue::Float(1.5),
+        ];
+
+        let encoded = serde_json::to_string(&values).unwrap();
+        assert_eq!(encoded, r#"["Abbey Road",null,-3,1.5]"#);
+        assert_eq!(
+            serde_json::from_str::<Vec<CursorValue>>(&encoded).unwrap(),
+            values
+        );
+    }
+
+    #[test]
+    fn datetime_and_decimal_keys_bind_from_text() {
+        let metadata = Metadata::for_tests();
+        let mut field = metadata.fields.values().next().unwrap().clone();
+        let mut values = BindValue::default();
+
+        field.data_type = DataType::DateTime.id();
+        CursorValue::Text("2021-03-04T05:06:07.5".into())
+            .bind(&field, &mut values)
+            .unwrap();
+        assert!(CursorValue::Text("yesterday".into())
+            .bind(&field, &mut values)
+            .is_err());
+
+        field.data_type = DataType::Decimal.id();
+        CursorValue::Text("-12.50".into())
+            .bind(&field, &mut values)
+            .unwrap();
+        assert!(CursorValue::Text("12,50".into())
+            .bind(&field, &mut values)
+            .is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::prelude::*;
+use std::collections::BTreeMap;
+
+/// Implemented by the `DbrTable` derive for every `#[relation(..)]` field.
+///
+/// The field is our side of the relation and has to point at the primary key of `P`.
+pub trait RelatedTo<P: DbrTable>: DbrTable {
+    fn foreign_key(&self) -> Option<P::Id>;
+}
+
+/// Relation from the table of `C` to the table of `P` in the metadata.
+pub fn lookup_relation<C, P>(context: &Context) -> Result<&Relation, DbrError>
+where
+    C: RelatedTo<P>,
+    P: DbrTable,
+{
+    let relation = context.metadata.find_relation(
+        SchemaIdentifier::Name(C::schema().to_owned()),
+        TableIdentifier::Name(C::table_name().to_owned()),
+        TableIdentifier::Name(P::table_name().to_owned()),
+    )?;
+
+    let to_field = context.metadata.lookup_field(relation.to_field_id)?;
+    if !to_field.is_primary_key {
+        return Err(DbrError::Unimplemented(format!(
+            "relation from {} to {} doesn't point at a primary key",
+            C::table_name(),
+            P::table_name()
+        )));
+    }
+
+    Ok(relation)
+}
+
+/// Follow a to-one relation, e.g. `song.album(&context)`
+///
+/// Anything prefetched under `name` is used first, then the record cache, so this only hits the
+/// database if nothing else is holding onto the record.
+pub async fn fetch_one<C, P>(
+    context: &Context,
+    record: &Active<C>,
+    name: &'static str,
+) -> Result<Option<Active<P>>, DbrError>
+where
+    C: RelatedTo<P>,
+    P: DbrTable,
+{
+    let foreign_key = {
+        let data = record.data().lock().map_err(|_| DbrError::PoisonError)?;
+        if let Some(mut prefetched) = data.relation::<P>(name) {
+            return Ok(prefetched.pop());
+        }
+
+        data.foreign_key()
+    };
+
+    match foreign_key {
+        Some(foreign_key) => P::get(context, foreign_key).await,
+        None => Ok(None),
+    }
+}
+
+/// Follow a to-many relation, e.g. `artist.albums(&context)`
+///
+/// This is the reverse of `C`'s relation to `P`, the related records can live on another instance.
+pub async fn fetch_many<P, C>(
+    context: &Context,
+    record: &Active<P>,
+    name: &'static str,
+) -> Result<Vec<Active<C>>, DbrError>
+where
+    P: DbrTable,
+    C: RelatedTo<P>,
+{
+    {
+        let data = record.data().lock().map_err(|_| DbrError::PoisonError)?;
+        if let Some(prefetched) = data.relation::<C>(name) {
+            return Ok(prefetched);
+        }
+    }
+
+    let relation = lookup_relation::<C, P>(context)?;
+    let from_field = context.metadata.lookup_field(relation.from_field_id)?;
+    let select = Select::filtered_on(
+        context,
+        relation.from_table_id,
+        from_field.name.clone(),
+        FilterOp::Eq,
+        FilterValue::scalar(record.id()),
+    )?;
+
+    select.fetch_active::<C>(context).await
+}
+
+/// Load a to-one relation for a batch of records, e.g. `prefetch song.album`
+///
+/// Records already in the cache are reused and the rest are fetched with a single query, see `DbrTable::get_many`.
+/// Each record remembers its related record under `name` so the accessor doesn't go back to the database,
+/// for as long as the handles in `records` are around.
+///
+/// Returns the distinct related records so the next hop of a prefetch can be loaded from them.
+pub async fn prefetch_one<C, P>(
+    context: &Context,
+    records: &[Active<C>],
+    name: &'static str,
+) -> Result<Vec<Active<P>>, DbrError>
+where
+    C: RelatedTo<P>,
+    P: DbrTable,
+{
+    let mut foreign_keys = Vec::with_capacity(records.len());
+    for record in records {
+        let data = record.data().lock().map_err(|_| DbrError::PoisonError)?;
+        foreign_keys.push(data.foreign_key());
+    }
+
+    let mut distinct_keys: Vec<P::Id> = foreign_keys.iter().flatten().cloned().collect();
+    distinct_keys.sort();
+    distinct_keys.dedup();
+
+    let related: BTreeMap<P::Id, Active<P>> = P::get_many(context, distinct_keys)
+        .await?
+        .into_iter()
+        .map(|record| (record.id(), record))
+        .collect();
+
+    for (record, foreign_key) in records.iter().zip(foreign_keys) {
+        let prefetched: Vec<Active<P>> = foreign_key
+            .and_then(|foreign_key| related.get(&foreign_key).cloned())
+            .into_iter()
+            .collect();
+        {
+            let mut data = record.data().lock().map_err(|_| DbrError::PoisonError)?;
+            data.set_relation(name, &prefetched);
+        }
+        record.hold(prefetched)?;
+    }
+
+    Ok(related.into_values().collect())
+}
+
+/// Load a to-many relation for a batch of records, e.g. `prefetch album.songs`
+///
+/// All of the related records are fetched with a single query and grouped by their foreign key.
+/// Each record remembers its group under `name` for as long as the handles in `records` are around.
+///
+/// Returns every related record so the next hop of a prefetch can be loaded from them.
+pub async fn prefetch_many<P, C>(
+    context: &Context,
+    records: &[Active<P>],
+    name: &'static str,
+) -> Result<Vec<Active<C>>, DbrError>
+where
+    P: DbrTable,
+    C: RelatedTo<P>,
+{
+    let mut ids: Vec<P::Id> = records.iter().map(|record| record.id()).collect();
+    ids.sort();
+    ids.dedup();
+
+    let children = if ids.len() > 0 {
+        let relation = lookup_relation::<C, P>(context)?;
+        let from_field = context.metadata.lookup_field(relation.from_field_id)?;
+        let select = Select::filtered_on(
+            context,
+            relation.from_table_id,
+            from_field.name.clone(),
+            FilterOp::In,
+            FilterValue::list(ids),
+        )?;
+
+        select.fetch_active::<C>(context).await?
+    } else {
+        Vec::new()
+    };
+
+    let mut grouped: BTreeMap<P::Id, Vec<Active<C>>> = BTreeMap::new();
+    for child in &children {
+        let foreign_key = {
+            let data = child.data().lock().map_err(|_| DbrError::PoisonError)?;
+            data.foreign_key()
+        };
+
+        if let Some(foreign_key) = foreign_key {
+            grouped.entry(foreign_key).or_default().push(child.clone());
+        }
+    }
+
+    for record in records {
+        let prefetched = grouped.get(&record.id()).cloned().unwrap_or_default();
+        {
+            let mut data = record.data().lock().map_err(|_| DbrError::PoisonError)?;
+            data.set_relation(name, &prefetched);
+        }
+        record.hold(prefetched)?;
+    }
+
+    Ok(children)
+}
@@ -0,0 +1,843 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use sqlx::{Acquire, MySql};
+
+use crate::filter::BindValue;
+use crate::metadata::TableInfo;
+use crate::prelude::*;
+
+/// Column as it was found in the database, before it has been matched up with the metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedField {
+    pub name: String,
+    pub data_type: u32,
+    pub is_nullable: bool,
+    pub is_signed: bool,
+    pub max_value: u64,
+    pub is_primary_key: bool,
+}
+
+/// Single column foreign key, composite ones don't map onto a relation so they are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedForeignKey {
+    pub from_field: String,
+    pub to_table: String,
+    pub to_field: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScannedTable {
+    pub name: String,
+    pub fields: Vec<ScannedField>,
+    pub foreign_keys: Vec<ScannedForeignKey>,
+
+    /// Columns with a type dbr doesn't know about, with the type we found.
+    pub unsupported: Vec<(String, String)>,
+}
+
+/// Look through the tables of an instance, using `information_schema` for MySQL and Postgres
+/// and `sqlite_master` with the table pragmas for SQLite.
+///
+/// Only tables visible from the connection are scanned, the database for MySQL and the
+/// current schema for Postgres. The queries go through `context` like any other, observer included.
+pub async fn scan(
+    context: &Context,
+    instance: &DbrInstance,
+) -> Result<Vec<ScannedTable>, DbrError> {
+    let (columns, foreign_keys) = match instance.module {
+        InstanceModule::MySql => scan_mysql(context, instance).await?,
+        InstanceModule::Postgres => scan_postgres(context, instance).await?,
+        InstanceModule::SQLite => scan_sqlite(context, instance).await?,
+    };
+
+    Ok(collect_tables(columns, foreign_keys))
+}
+
+/// Group the columns and foreign keys of a scan by table.
+fn collect_tables(
+    columns: Vec<ScannedColumn>,
+    foreign_keys: Vec<ForeignKeyRow>,
+) -> Vec<ScannedTable> {
+    let mut tables: Vec<ScannedTable> = Vec::new();
+    let mut table_indices = HashMap::new();
+    for column in columns {
+        let index = *table_indices
+            .entry(column.table.clone())
+            .or_insert_with(|| {
+                tables.push(ScannedTable {
+                    name: column.table.clone(),
+                    fields: Vec::new(),
+                    foreign_keys: Vec::new(),
+                    unsupported: Vec::new(),
+                });
+                tables.len() - 1
+            });
+
+        let table = &mut tables[index];
+        match column.field() {
+            Some(field) => table.fields.push(field),
+            None => {
+                tracing::warn!(
+                    table = column.table.as_str(),
+                    field = column.name.as_str(),
+                    column_type = column.column_type.as_str(),
+                    "skipping field with unsupported type"
+                );
+                table.unsupported.push((column.name, column.column_type));
+            }
+        }
+    }
+
+    // Composite keys show up once per column under the same constraint.
+    let mut columns_per_key = HashMap::new();
+    for key in &foreign_keys {
+        *columns_per_key.entry((&key.0, &key.1)).or_insert(0) += 1;
+    }
+    let single: Vec<_> = foreign_keys
+        .iter()
+        .filter(|key| columns_per_key[&(&key.0, &key.1)] == 1)
+        .cloned()
+        .collect();
+
+    for (_, table, from_field, to_table, to_field) in single {
+        if let Some(index) = table_indices.get(&table) {
+            tables[*index].foreign_keys.push(ScannedForeignKey {
+                from_field,
+                to_table,
+                to_field,
+            });
+        }
+    }
+
+    tables
+}
+
+struct ScannedColumn {
+    table: String,
+    name: String,
+    column_type: String,
+    length: Option<i64>,
+    is_nullable: bool,
+    is_primary_key: bool,
+}
+
+impl ScannedColumn {
+    fn field(&self) -> Option<ScannedField> {
+        let (base, length, unsigned) = parse_column_type(&self.column_type);
+        let data_type = DataType::from_type_name(&base)?;
+        let max_value = length
+            .or(self.length.and_then(|length| u64::try_from(length).ok()))
+            .unwrap_or_default();
+
+        Some(ScannedField {
+            name: self.name.clone(),
+            data_type: data_type.id(),
+            // Primary keys are never null, SQLite just doesn't mark them as such.
+            is_nullable: self.is_nullable && !self.is_primary_key,
+            is_signed: data_type.is_numeric() && !unsigned,
+            max_value,
+            is_primary_key: self.is_primary_key,
+        })
+    }
+}
+
+/// (constraint, table, field, referenced table, referenced field)
+type ForeignKeyRow = (String, String, String, String, String);
+
+async fn scan_mysql(
+    context: &Context,
+    instance: &DbrInstance,
+) -> Result<(Vec<ScannedColumn>, Vec<ForeignKeyRow>), DbrError> {
+    // Casting everything since information_schema is a mix of binary strings and unsigned ints depending on the version.
+    let rows: Vec<(String, String, String, String, Option<i64>, i64)> = context
+        .fetch_all_as(
+            instance,
+            r"SELECT CAST(c.table_name AS CHAR), CAST(c.column_name AS CHAR), CAST(c.column_type AS CHAR), CAST(c.is_nullable AS CHAR),
+            CAST(COALESCE(c.character_maximum_length, c.numeric_precision) AS SIGNED), CAST(c.column_key = 'PRI' AS SIGNED)
+        FROM information_schema.columns c
+        JOIN information_schema.tables t ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+        WHERE c.table_schema = DATABASE() AND t.table_type = 'BASE TABLE'
+        ORDER BY c.table_name, c.ordinal_position",
+            BindValue::default(),
+        )
+        .await?;
+
+    let foreign_keys = context
+        .fetch_all_as(
+            instance,
+            r"SELECT CAST(constraint_name AS CHAR), CAST(table_name AS CHAR), CAST(column_name AS CHAR), CAST(referenced_table_name AS CHAR), CAST(referenced_column_name AS CHAR)
+        FROM information_schema.key_column_usage
+        WHERE table_schema = DATABASE() AND referenced_table_schema = DATABASE()",
+            BindValue::default(),
+        )
+        .await?;
+
+    Ok((information_schema_columns(rows), foreign_keys))
+}
+
+async fn scan_postgres(
+    context: &Context,
+    instance: &DbrInstance,
+) -> Result<(Vec<ScannedColumn>, Vec<ForeignKeyRow>), DbrError> {
+    // information_schema uses its own domains which don't decode as text and numbers.
+    let rows: Vec<(String, String, String, String, Option<i64>, i64)> = context
+        .fetch_all_as(
+            instance,
+            r"SELECT c.table_name::text, c.column_name::text, c.data_type::text, c.is_nullable::text,
+            COALESCE(c.character_maximum_length, c.numeric_precision)::bigint,
+            (CASE WHEN EXISTS (
+                SELECT 1 FROM information_schema.table_constraints tc
+                JOIN information_schema.key_column_usage kcu
+                    ON kcu.constraint_schema = tc.constraint_schema AND kcu.constraint_name = tc.constraint_name
+                WHERE tc.constraint_type = 'PRIMARY KEY' AND tc.table_schema = c.table_schema
+                    AND tc.table_name = c.table_name AND kcu.column_name = c.column_name
+            ) THEN 1 ELSE 0 END)::bigint
+        FROM information_schema.columns c
+        JOIN information_schema.tables t ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+        WHERE c.table_schema = current_schema() AND t.table_type = 'BASE TABLE'
+        ORDER BY c.table_name, c.ordinal_position",
+            BindValue::default(),
+        )
+        .await?;
+
+    let foreign_keys = context
+        .fetch_all_as(
+            instance,
+            r"SELECT tc.constraint_name::text, kcu.table_name::text, kcu.column_name::text, ccu.table_name::text, ccu.column_name::text
+        FROM information_schema.table_constraints tc
+        JOIN information_schema.key_column_usage kcu
+            ON kcu.constraint_schema = tc.constraint_schema AND kcu.constraint_name = tc.constraint_name
+        JOIN information_schema.constraint_column_usage ccu
+            ON ccu.constraint_schema = tc.constraint_schema AND ccu.constraint_name = tc.constraint_name
+        WHERE tc.constraint_type = 'FOREIGN KEY' AND tc.table_schema = current_schema()
+            AND ccu.table_schema = current_schema()",
+            BindValue::default(),
+        )
+        .await?;
+
+    Ok((information_schema_columns(rows), foreign_keys))
+}
+
+fn information_schema_columns(
+    rows: Vec<(String, String, String, String, Option<i64>, i64)>,
+) -> Vec<ScannedColumn> {
+    rows.into_iter()
+        .map(
+            |(table, name, column_type, is_nullable, length, is_primary_key)| ScannedColumn {
+                table,
+                name,
+                column_type,
+                length,
+                is_nullable: is_nullable.eq_ignore_ascii_case("YES"),
+                is_primary_key: is_primary_key != 0,
+            },
+        )
+        .collect()
+}
+
+async fn scan_sqlite(
+    context: &Context,
+    instance: &DbrInstance,
+) -> Result<(Vec<ScannedColumn>, Vec<ForeignKeyRow>), DbrError> {
+    let table_names: Vec<(String,)> = context
+        .fetch_all_as(
+            instance,
+            r"SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            BindValue::default(),
+        )
+        .await?;
+
+    let mut columns = Vec::new();
+    let mut foreign_keys = Vec::new();
+    for (table,) in table_names {
+        let rows: Vec<(String, String, i64, i64)> = context
+            .fetch_all_as(
+                instance,
+                r#"SELECT name, type, "notnull", pk FROM pragma_table_info(?) ORDER BY cid"#,
+                table_argument(&table),
+            )
+            .await?;
+
+        for (name, column_type, not_null, primary_key) in rows {
+            columns.push(ScannedColumn {
+                table: table.clone(),
+                name,
+                column_type,
+                length: None,
+                is_nullable: not_null == 0,
+                is_primary_key: primary_key != 0,
+            });
+        }
+
+        let keys: Vec<(String, String, String, Option<String>)> = context
+            .fetch_all_as(
+                instance,
+                r#"SELECT CAST(id AS TEXT), "from", "table", "to" FROM pragma_foreign_key_list(?)"#,
+                table_argument(&table),
+            )
+            .await?;
+
+        for (id, from_field, to_table, to_field) in keys {
+            // No column means it references the primary key, which we don't know yet.
+            let to_field = to_field.unwrap_or_default();
+            foreign_keys.push((id, table.clone(), from_field, to_table, to_field));
+        }
+    }
+
+    // Fill in the implicit primary key references now that every table has been seen.
+    for key in &mut foreign_keys {
+        if key.4.is_empty() {
+            if let Some(primary_key) = columns
+                .iter()
+                .find(|column| column.table == key.3 && column.is_primary_key)
+            {
+                key.4 = primary_key.name.clone();
+            }
+        }
+    }
+
+    Ok((columns, foreign_keys))
+}
+
+fn table_argument(table: &str) -> BindValue {
+    use sqlx::Arguments;
+
+    let mut arguments = BindValue::default();
+    arguments.add(table.to_owned());
+    arguments
+}
+
+/// Split a column type like `int(10) unsigned` or `varchar(255)` into its base type, length and signedness.
+fn parse_column_type(column_type: &str) -> (String, Option<u64>, bool) {
+    let column_type = column_type.trim().to_lowercase();
+    let unsigned = column_type
+        .split_whitespace()
+        .any(|word| word == "unsigned");
+
+    let (base, arguments) = match column_type.find('(') {
+        Some(index) => (&column_type[..index], Some(&column_type[index + 1..])),
+        None => (column_type.as_str(), None),
+    };
+
+    let base = base
+        .split_whitespace()
+        .filter(|word| *word != "unsigned" && *word != "zerofill")
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    // Only the first argument, `decimal(10,2)` is 10 digits total.
+    let length = arguments
+        .and_then(|arguments| arguments.split(|c: char| c == ',' || c == ')').next())
+        .and_then(|length| length.trim().parse().ok());
+
+    (base, length, unsigned)
+}
+
+/// What it takes to bring the metadata of a schema in line with a scan, see `Metadata::diff_scan`
+///
+/// New rows already have ids picked after the highest ones in the metadata so they can refer to each other.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataChanges {
+    pub new_tables: Vec<TableInfo>,
+    pub new_fields: Vec<Field>,
+    pub new_relations: Vec<Relation>,
+
+    /// Fields that exist in both but differ, the old field first.
+    pub changed_fields: Vec<(Field, Field)>,
+
+    /// Only ever reported, write back doesn't remove anything.
+    pub missing_tables: Vec<TableInfo>,
+    pub missing_fields: Vec<Field>,
+    pub missing_relations: Vec<Relation>,
+
+    /// Table names on either end of the new relations, dbr_relationships keeps them around too.
+    relation_names: HashMap<RelationId, (String, String)>,
+}
+
+impl Metadata {
+    /// Scan an instance and compare it against the metadata for its schema.
+    pub async fn scan_instance(
+        &self,
+        context: &Context,
+        instance: &DbrInstance,
+    ) -> Result<MetadataChanges, DbrError> {
+        let scanned = scan(context, instance).await?;
+        self.diff_scan(instance.info.schema_id(), &scanned)
+    }
+
+    pub fn diff_scan(
+        &self,
+        schema_id: SchemaId,
+        scanned: &[ScannedTable],
+    ) -> Result<MetadataChanges, DbrError> {
+        let schema = self.lookup_schema(SchemaIdentifier::Id(schema_id))?;
+
+        let mut next_table = self.tables.keys().max().copied().unwrap_or_default();
+        let mut next_field = self.fields.keys().max().copied().unwrap_or_default();
+        let mut next_relation = self.relations.keys().max().copied().unwrap_or_default();
+
+        let mut changes = MetadataChanges::default();
+
+        // Every table and field of the scan by name, whether it is new or not.
+        let mut table_ids = HashMap::new();
+        let mut field_ids = HashMap::new();
+
+        for scanned_table in scanned {
+            let existing = schema
+                .tables
+                .get(&scanned_table.name)
+                .map(|table_id| self.lookup_table(*table_id))
+                .transpose()?;
+
+            let table_id = match existing {
+                Some(table) => table.id,
+                None => {
+                    next_table = next_table.next();
+                    changes.new_tables.push(TableInfo::new(
+                        next_table,
+                        schema_id,
+                        scanned_table.name.clone(),
+                    ));
+                    next_table
+                }
+            };
+            table_ids.insert(scanned_table.name.as_str(), table_id);
+
+            for scanned_field in &scanned_table.fields {
+                let existing = existing
+                    .and_then(|table| table.fields.get(&scanned_field.name))
+                    .map(|field_id| self.lookup_field(*field_id))
+                    .transpose()?;
+
+                let field_id = match existing {
+                    Some(field) => {
+                        let updated = Field {
+                            data_type: scanned_field.data_type,
+                            is_nullable: scanned_field.is_nullable,
+                            is_signed: scanned_field.is_signed,
+                            max_value: scanned_field.max_value,
+                            is_primary_key: scanned_field.is_primary_key,
+                            ..field.clone()
+                        };
+
+                        if !same_field(field, &updated) {
+                            changes.changed_fields.push((field.clone(), updated));
+                        }
+
+                        field.id
+                    }
+                    None => {
+                        next_field = next_field.next();
+                        changes.new_fields.push(Field {
+                            id: next_field,
+                            table_id,
+                            name: scanned_field.name.clone(),
+                            data_type: scanned_field.data_type,
+                            is_nullable: scanned_field.is_nullable,
+                            is_signed: scanned_field.is_signed,
+                            max_value: scanned_field.max_value,
+                            is_primary_key: scanned_field.is_primary_key,
+                            trans_id: None,
+                            regex: None,
+                            default_value: None,
+                        });
+                        next_field
+                    }
+                };
+                field_ids.insert(
+                    (scanned_table.name.as_str(), scanned_field.name.as_str()),
+                    field_id,
+                );
+            }
+
+            if let Some(table) = existing {
+                let mut names: HashSet<&str> = scanned_table
+                    .fields
+                    .iter()
+                    .map(|field| field.name.as_str())
+                    .collect();
+                names.extend(
+                    scanned_table
+                        .unsupported
+                        .iter()
+                        .map(|(name, _)| name.as_str()),
+                );
+
+                for (name, field_id) in &table.fields {
+                    if !names.contains(name.as_str()) {
+                        changes
+                            .missing_fields
+                            .push(self.lookup_field(*field_id)?.clone());
+                    }
+                }
+            }
+        }
+
+        for (name, table_id) in &schema.tables {
+            if !table_ids.contains_key(name.as_str()) {
+                changes
+                    .missing_tables
+                    .push(self.lookup_table(*table_id)?.info.clone());
+            }
+        }
+
+        let mut scanned_relations = HashSet::new();
+        for scanned_table in scanned {
+            for key in &scanned_table.foreign_keys {
+                let from_field =
+                    field_ids.get(&(scanned_table.name.as_str(), key.from_field.as_str()));
+                let to_field = field_ids.get(&(key.to_table.as_str(), key.to_field.as_str()));
+                let (from_field_id, to_field_id) = match (from_field, to_field) {
+                    (Some(from), Some(to)) => (*from, *to),
+                    // One of the ends has a type we skipped.
+                    _ => continue,
+                };
+                scanned_relations.insert((from_field_id, to_field_id));
+
+                let exists = self.relations.values().any(|relation| {
+                    relation.from_field_id == from_field_id && relation.to_field_id == to_field_id
+                });
+
+                if !exists {
+                    next_relation = next_relation.next();
+                    changes.relation_names.insert(
+                        next_relation,
+                        (scanned_table.name.clone(), key.to_table.clone()),
+                    );
+                    changes.new_relations.push(Relation {
+                        id: next_relation,
+                        from_table_id: table_ids[scanned_table.name.as_str()],
+                        from_field_id,
+                        to_table_id: table_ids[key.to_table.as_str()],
+                        to_field_id,
+                    });
+                }
+            }
+        }
+
+        for relation in self.relations.values() {
+            let in_schema = schema
+                .tables
+                .values()
+                .any(|table_id| *table_id == relation.from_table_id);
+            if in_schema
+                && !scanned_relations.contains(&(relation.from_field_id, relation.to_field_id))
+            {
+                changes.missing_relations.push(relation.clone());
+            }
+        }
+
+        changes.new_tables.sort_by_key(|table| table.id);
+        changes.new_fields.sort_by_key(|field| field.id);
+        changes.new_relations.sort_by_key(|relation| relation.id);
+        changes.changed_fields.sort_by_key(|(field, _)| field.id);
+        changes.missing_tables.sort_by_key(|table| table.id);
+        changes.missing_fields.sort_by_key(|field| field.id);
+        changes
+            .missing_relations
+            .sort_by_key(|relation| relation.id);
+
+        Ok(changes)
+    }
+}
+
+fn same_field(a: &Field, b: &Field) -> bool {
+    a.data_type == b.data_type
+        && a.is_nullable == b.is_nullable
+        && a.is_signed == b.is_signed
+        && a.max_value == b.max_value
+        && a.is_primary_key == b.is_primary_key
+}
+
+impl MetadataChanges {
+    pub fn is_empty(&self) -> bool {
+        self.new_tables.is_empty()
+            && self.new_fields.is_empty()
+            && self.new_relations.is_empty()
+            && self.changed_fields.is_empty()
+            && self.missing_tables.is_empty()
+            && self.missing_fields.is_empty()
+            && self.missing_relations.is_empty()
+    }
+
+    /// Apply the new and changed rows to loaded metadata, e.g. to save it with `Metadata::save_to_path`
+    pub fn apply_to(&self, metadata: &mut Metadata) -> Result<(), DbrError> {
+        let mut snapshot = metadata.snapshot();
+        snapshot.tables.extend(self.new_tables.iter().cloned());
+        snapshot.fields.extend(self.new_fields.iter().cloned());
+        snapshot
+            .relations
+            .extend(self.new_relations.iter().cloned());
+        for (_, updated) in &self.changed_fields {
+            if let Some(field) = snapshot
+                .fields
+                .iter_mut()
+                .find(|field| field.id == updated.id)
+            {
+                *field = updated.clone();
+            }
+        }
+
+        *metadata = snapshot.into_metadata()?;
+        Ok(())
+    }
+
+    /// Write the new and changed rows into the dbr tables, nothing missing is removed.
+    ///
+    /// Everything is written in one transaction, so a failure part way leaves the dbr tables as they were.
+    pub async fn write_back<'a, A>(&self, connection: A) -> Result<(), DbrError>
+    where
+        A: Acquire<'a, Database = MySql>,
+    {
+        let mut transaction = connection.begin().await?;
+
+        for table in &self.new_tables {
+            sqlx::query(r"INSERT INTO dbr_tables (table_id, schema_id, name) VALUES (?, ?, ?)")
+                .bind(table.id)
+                .bind(table.schema_id)
+                .bind(&table.name)
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        for field in &self.new_fields {
+            sqlx::query(r"INSERT INTO dbr_fields (field_id, table_id, name, data_type, is_nullable, is_signed, max_value, is_pkey) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+                .bind(field.id)
+                .bind(field.table_id)
+                .bind(&field.name)
+                .bind(field.data_type)
+                .bind(field.is_nullable)
+                .bind(field.is_signed)
+                .bind(field.max_value)
+                .bind(field.is_primary_key)
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        for (_, field) in &self.changed_fields {
+            sqlx::query(r"UPDATE dbr_fields SET data_type = ?, is_nullable = ?, is_signed = ?, max_value = ?, is_pkey = ? WHERE field_id = ?")
+                .bind(field.data_type)
+                .bind(field.is_nullable)
+                .bind(field.is_signed)
+                .bind(field.max_value)
+                .bind(field.is_primary_key)
+                .bind(field.id)
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        for relation in &self.new_relations {
+            let (from_name, to_name) = self.relation_names.get(&relation.id).cloned().unzip();
+            // Type 2 is `childof`, the side holding the foreign key.
+            sqlx::query(r"INSERT INTO dbr_relationships (relationship_id, from_name, from_table_id, from_field_id, to_name, to_table_id, to_field_id, type) VALUES (?, ?, ?, ?, ?, ?, ?, 2)")
+                .bind(relation.id)
+                .bind(from_name)
+                .bind(relation.from_table_id)
+                .bind(relation.from_field_id)
+                .bind(to_name)
+                .bind(relation.to_table_id)
+                .bind(relation.to_field_id)
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for MetadataChanges {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for table in &self.new_tables {
+            writeln!(f, "+ table {}", table.name)?;
+        }
+        for field in &self.new_fields {
+            writeln!(f, "+ field {} on table {:?}", field.name, field.table_id)?;
+        }
+        for (old, new) in &self.changed_fields {
+            writeln!(f, "~ field {}: {:?} -> {:?}", old.name, old, new)?;
+        }
+        for relation in &self.new_relations {
+            writeln!(
+                f,
+                "+ relation {:?} -> {:?}",
+                relation.from_field_id, relation.to_field_id
+            )?;
+        }
+        for table in &self.missing_tables {
+            writeln!(f, "- table {}", table.name)?;
+        }
+        for field in &self.missing_fields {
+            writeln!(f, "- field {} on table {:?}", field.name, field.table_id)?;
+        }
+        for relation in &self.missing_relations {
+            writeln!(
+                f,
+                "- relation {:?} -> {:?}",
+                relation.from_field_id, relation.to_field_id
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(table: &str, name: &str, column_type: &str, is_primary_key: bool) -> ScannedColumn {
+        ScannedColumn {
+            table: table.to_owned(),
+            name: name.to_owned(),
+            column_type: column_type.to_owned(),
+            length: None,
+            is_nullable: false,
+            is_primary_key,
+        }
+    }
+
+    fn foreign_key(
+        constraint: &str,
+        table: &str,
+        from: &str,
+        to_table: &str,
+        to: &str,
+    ) -> ForeignKeyRow {
+        (
+            constraint.to_owned(),
+            table.to_owned(),
+            from.to_owned(),
+            to_table.to_owned(),
+            to.to_owned(),
+        )
+    }
+
+    fn scanned_field(column_type: &str, is_nullable: bool, is_primary_key: bool) -> ScannedField {
+        ScannedColumn {
+            is_nullable,
+            ..column("song", "", column_type, is_primary_key)
+        }
+        .field()
+        .unwrap()
+    }
+
+    #[test]
+    fn column_types() {
+        assert_eq!(
+            parse_column_type("int(10) unsigned"),
+            ("int".to_owned(), Some(10), true)
+        );
+        assert_eq!(
+            parse_column_type("decimal(10,2)"),
+            ("decimal".to_owned(), Some(10), false)
+        );
+        assert_eq!(
+            parse_column_type("double precision"),
+            ("double precision".to_owned(), None, false)
+        );
+
+        let field = scanned_field("int(10) unsigned", false, true);
+        assert_eq!(field.data_type, DataType::Int.id());
+        assert_eq!(field.max_value, 10);
+        assert!(!field.is_signed);
+
+        let field = scanned_field("decimal(10,2)", true, false);
+        assert_eq!(field.data_type, DataType::Decimal.id());
+        assert_eq!(field.max_value, 10);
+        assert!(field.is_signed);
+        assert!(field.is_nullable);
+    }
+
+    #[test]
+    fn composite_foreign_keys_are_skipped() {
+        let columns = vec![
+            column("album", "id", "int", true),
+            column("album", "artist_id", "int", false),
+            column("song", "id", "int", true),
+            column("song", "album_id", "int", false),
+            column("song", "artist_id", "int", false),
+        ];
+        let foreign_keys = vec![
+            foreign_key("song_album", "song", "album_id", "album", "id"),
+            foreign_key("song_album_artist", "song", "album_id", "album", "id"),
+            foreign_key(
+                "song_album_artist",
+                "song",
+                "artist_id",
+                "album",
+                "artist_id",
+            ),
+        ];
+
+        let tables = collect_tables(columns, foreign_keys);
+        let song = tables.iter().find(|table| table.name == "song").unwrap();
+        assert_eq!(
+            song.foreign_keys,
+            vec![ScannedForeignKey {
+                from_field: "album_id".to_owned(),
+                to_table: "album".to_owned(),
+                to_field: "id".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_against_metadata() {
+        let metadata = Metadata::for_tests();
+        let schema_id = metadata
+            .lookup_schema(SchemaIdentifier::Name("ops".to_owned()))
+            .unwrap()
+            .id;
+
+        let scanned = collect_tables(
+            vec![
+                column("song", "id", "int(10) unsigned", true),
+                ScannedColumn {
+                    is_nullable: true,
+                    ..column("song", "name", "varchar(32)", false)
+                },
+                column("song", "version", "int(10) unsigned", false),
+                column("song", "album_id", "int(10) unsigned", false),
+                column("album", "id", "int(10) unsigned", true),
+            ],
+            vec![foreign_key("song_album", "song", "album_id", "album", "id")],
+        );
+
+        let changes = metadata.diff_scan(schema_id, &scanned).unwrap();
+
+        let new_tables: Vec<_> = changes
+            .new_tables
+            .iter()
+            .map(|table| table.name.as_str())
+            .collect();
+        assert_eq!(new_tables, ["album"]);
+
+        let new_fields: Vec<_> = changes
+            .new_fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        assert_eq!(new_fields, ["album_id", "id"]);
+        assert_eq!(changes.new_fields[1].table_id, changes.new_tables[0].id);
+
+        assert_eq!(changes.changed_fields.len(), 1);
+        let (old, new) = &changes.changed_fields[0];
+        assert_eq!(old.name, "name");
+        assert_eq!((old.max_value, new.max_value), (16, 32));
+
+        let missing_fields: Vec<_> = changes
+            .missing_fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        assert_eq!(missing_fields, ["plays"]);
+        assert!(changes.missing_tables.is_empty());
+
+        assert_eq!(changes.new_relations.len(), 1);
+        let relation = &changes.new_relations[0];
+        assert_eq!(relation.from_field_id, changes.new_fields[0].id);
+        assert_eq!(relation.to_field_id, changes.new_fields[1].id);
+        assert_eq!(relation.to_table_id, changes.new_tables[0].id);
+    }
+}
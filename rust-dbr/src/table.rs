@@ -1,10 +1,13 @@
 use crate::prelude::*;
+use futures::future::BoxFuture;
+use sqlx::{any::AnyRow, Any};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
 use std::hash::Hash;
 
 pub trait DbrTable
 where
-    Self: Debug + Send + Sync + Sized + Clone + 'static,
+    Self: for<'r> sqlx::FromRow<'r, AnyRow> + Debug + Send + Sync + Sized + Clone + Unpin + 'static,
 {
     type Id: Debug
         + Send
@@ -16,10 +19,206 @@ where
         + PartialOrd
         + Ord
         + Hash
+        + for<'q> sqlx::Encode<'q, Any>
+        + for<'r> sqlx::Decode<'r, Any>
+        + sqlx::Type<Any>
         + 'static;
     type ActiveModel: ActiveModel<Self>;
-    type PartialModel: PartialModel<Self>;
+    type PartialModel: PartialModel<Self> + Clone + Send + 'static;
+
+    /// `schema.table_name` as given to `#[table = ".."]`
+    const TABLE: &'static str;
+
     fn schema() -> &'static str;
     fn table_name() -> &'static str;
     fn fields() -> Vec<&'static str>;
+    fn id(&self) -> Self::Id;
+
+    /// Look up a record by its primary key, see `get_many`
+    fn get<'a>(
+        context: &'a Context,
+        id: Self::Id,
+    ) -> BoxFuture<'a, Result<Option<Active<Self>>, DbrError>> {
+        Box::pin(async move { Ok(Self::get_many(context, vec![id]).await?.pop()) })
+    }
+
+    /// Look up records by their primary keys.
+    ///
+    /// Records still alive in the record cache are handed out as is, the rest are fetched in one query.
+    /// Inside of a transaction that's the transaction's own copies of the records it has changed.
+    /// The records come back in the order of `ids`, ones that don't exist are left out.
+    fn get_many<'a>(
+        context: &'a Context,
+        ids: Vec<Self::Id>,
+    ) -> BoxFuture<'a, Result<Vec<Active<Self>>, DbrError>> {
+        Box::pin(async move {
+            let instance = context.instance_for::<Self>()?;
+
+            let mut found: BTreeMap<Self::Id, Active<Self>> = BTreeMap::new();
+            let mut missing = BTreeSet::new();
+            for id in &ids {
+                if found.contains_key(id) || missing.contains(id) {
+                    continue;
+                }
+
+                match context.cached_record::<Self>(&instance, id.clone())? {
+                    Some(record) => {
+                        found.insert(id.clone(), record);
+                    }
+                    None => {
+                        missing.insert(id.clone());
+                    }
+                }
+            }
+
+            if missing.len() > 0 {
+                let table = context.metadata.lookup_dbr_table::<Self>()?;
+                let primary_key = context.metadata.lookup_primary_key(table.id)?;
+                let select = Select::filtered_on(
+                    context,
+                    table.id,
+                    primary_key.name.clone(),
+                    FilterOp::In,
+                    FilterValue::list(missing),
+                )?;
+
+                for record in select.fetch_active::<Self>(context).await? {
+                    found.insert(record.id(), record);
+                }
+            }
+
+            Ok(ids.iter().filter_map(|id| found.get(id).cloned()).collect())
+        })
+    }
+}
+
+/// `ops.song` of `Metadata::for_tests`, written out the way the derive would.
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::ops::{Deref, DerefMut};
+
+    use super::*;
+    use crate::filter::BindValue;
+    use crate::validation::FieldValue;
+
+    #[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+    pub(crate) struct Song {
+        pub id: i64,
+        pub name: Option<String>,
+        pub plays: i16,
+        pub version: i64,
+    }
+
+    #[derive(Debug, Default, Clone)]
+    pub(crate) struct PartialSong {
+        pub name: Option<Option<String>>,
+        pub plays: Option<i16>,
+        pub version: Option<i64>,
+    }
+
+    pub(crate) fn song(version: i64) -> Song {
+        Song {
+            id: 1,
+            name: Some("Help".into()),
+            plays: 0,
+            version,
+        }
+    }
+
+    impl PartialModel<Song> for PartialSong {
+        fn apply<R>(self, record: &mut R) -> Result<(), DbrError>
+        where
+            R: Deref<Target = Song> + DerefMut,
+        {
+            if let Some(name) = self.name {
+                record.name = name;
+            }
+            if let Some(plays) = self.plays {
+                record.plays = plays;
+            }
+            if let Some(version) = self.version {
+                record.version = version;
+            }
+            Ok(())
+        }
+        fn id(&self) -> Option<i64> {
+            None
+        }
+        fn fields(&self) -> Vec<&'static str> {
+            let mut fields = Vec::new();
+            if self.name.is_some() {
+                fields.push("name");
+            }
+            if self.plays.is_some() {
+                fields.push("plays");
+            }
+            if self.version.is_some() {
+                fields.push("version");
+            }
+            fields
+        }
+        fn from_record(record: &Song, fields: &[&str]) -> Self {
+            Self {
+                name: Some(record.name.clone()).filter(|_| fields.contains(&"name")),
+                plays: Some(record.plays).filter(|_| fields.contains(&"plays")),
+                version: Some(record.version).filter(|_| fields.contains(&"version")),
+            }
+        }
+        fn into_arguments(self) -> (Vec<&'static str>, BindValue) {
+            (self.fields(), BindValue::default())
+        }
+        fn field_values(&self) -> Vec<(&'static str, FieldValue)> {
+            Vec::new()
+        }
+        fn version(field: &str, version: u64) -> Option<Self> {
+            let version = i64::try_from(version).ok().filter(|_| field == "version")?;
+            Some(Self {
+                version: Some(version),
+                ..Default::default()
+            })
+        }
+    }
+
+    impl DbrTable for Song {
+        type Id = i64;
+        type ActiveModel = Active<Song>;
+        type PartialModel = PartialSong;
+        const TABLE: &'static str = "ops.song";
+        fn schema() -> &'static str {
+            "ops"
+        }
+        fn table_name() -> &'static str {
+            "song"
+        }
+        fn fields() -> Vec<&'static str> {
+            vec!["id", "name", "plays", "version"]
+        }
+        fn id(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[tokio::test]
+    async fn get_sees_the_transactions_own_writes() {
+        let context = Context::for_tests();
+        let instance = context.instance_for::<Song>().unwrap();
+        let record = context.register_record(&instance, song(0)).unwrap();
+
+        let transaction = context.begin_transaction();
+        let yesterday = PartialSong {
+            name: Some(Some("Yesterday".into())),
+            ..Default::default()
+        };
+        transaction.apply_partial(&record, yesterday).unwrap();
+
+        let inside = Song::get(&transaction, 1).await.unwrap().unwrap();
+        assert_eq!(
+            inside.snapshot().unwrap().name.as_deref(),
+            Some("Yesterday")
+        );
+
+        // Nothing outside of the transaction sees it until it commits.
+        let outside = Song::get(&context, 1).await.unwrap().unwrap();
+        assert_eq!(outside.snapshot().unwrap().name.as_deref(), Some("Help"));
+    }
 }
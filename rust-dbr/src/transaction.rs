@@ -0,0 +1,162 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::OwnedMutexGuard;
+
+use crate::prelude::*;
+
+type PendingWrite = Box<dyn FnOnce() -> Result<(), DbrError> + Send>;
+type InstanceTransaction = Arc<tokio::sync::Mutex<Option<sqlx::Transaction<'static, sqlx::Any>>>>;
+
+/// State shared by every clone of a transactional `Context`.
+///
+/// One transaction is opened per DBR instance, lazily the first time a statement is run against it.
+/// Committing is not atomic across instances, if committing one of them fails the rest are rolled back
+/// but the ones already committed stay that way. Keep a transaction to one instance if that matters.
+pub struct Transaction {
+    transactions: Mutex<HashMap<DbrInstanceId, InstanceTransaction>>,
+
+    /// Records loaded or changed inside of the transaction, per instance.
+    ///
+    /// These are the transaction's own copies, the shared record caches only see them once it commits.
+    caches: Mutex<HashMap<DbrInstanceId, Arc<DbrRecordCache>>>,
+
+    /// Keeps the copies in `caches` alive until the transaction is over.
+    records: Mutex<Vec<Arc<dyn Any + Send + Sync>>>,
+
+    /// Writes to the shared record caches, held back until the transaction is committed.
+    pending: Mutex<Vec<PendingWrite>>,
+}
+
+/// Open transaction of one instance, see `Transaction::lock`
+pub struct TransactionGuard(OwnedMutexGuard<Option<sqlx::Transaction<'static, sqlx::Any>>>);
+
+impl Deref for TransactionGuard {
+    type Target = sqlx::Transaction<'static, sqlx::Any>;
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().expect("transaction to have been opened")
+    }
+}
+
+impl DerefMut for TransactionGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.as_mut().expect("transaction to have been opened")
+    }
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self {
+            transactions: Mutex::new(HashMap::new()),
+            caches: Mutex::new(HashMap::new()),
+            records: Mutex::new(Vec::new()),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Open transaction for an instance, beginning one if we haven't touched the instance yet.
+    ///
+    /// Only statements against the same instance wait on each other.
+    pub async fn lock(&self, instance: &DbrInstance) -> Result<TransactionGuard, DbrError> {
+        let id = instance.info.id();
+        let transaction = {
+            let mut transactions = self
+                .transactions
+                .lock()
+                .map_err(|_| DbrError::PoisonError)?;
+            transactions.entry(id).or_default().clone()
+        };
+
+        let mut guard = transaction.lock_owned().await;
+        if guard.is_none() {
+            *guard = Some(instance.pool.begin().await?);
+        }
+
+        Ok(TransactionGuard(guard))
+    }
+
+    /// The transaction's own record cache for an instance.
+    pub fn cache(&self, instance: &DbrInstance) -> Result<Arc<DbrRecordCache>, DbrError> {
+        let mut caches = self.caches.lock().map_err(|_| DbrError::PoisonError)?;
+        Ok(caches
+            .entry(instance.info.id())
+            .or_insert_with(|| Arc::new(DbrRecordCache::new()))
+            .clone())
+    }
+
+    /// Keep a record of the transaction's caches alive until the transaction is over.
+    pub fn hold<R: Any + Send + Sync>(&self, record: Arc<R>) -> Result<(), DbrError> {
+        let mut records = self.records.lock().map_err(|_| DbrError::PoisonError)?;
+        records.push(record);
+        Ok(())
+    }
+
+    pub fn defer<F>(&self, write: F) -> Result<(), DbrError>
+    where
+        F: FnOnce() -> Result<(), DbrError> + Send + 'static,
+    {
+        let mut pending = self.pending.lock().map_err(|_| DbrError::PoisonError)?;
+        pending.push(Box::new(write));
+        Ok(())
+    }
+
+    fn take_transactions(&self) -> Result<Vec<InstanceTransaction>, DbrError> {
+        let mut transactions = self
+            .transactions
+            .lock()
+            .map_err(|_| DbrError::PoisonError)?;
+        Ok(std::mem::take(&mut *transactions).into_values().collect())
+    }
+
+    fn clear_records(&self) -> Result<(), DbrError> {
+        self.caches
+            .lock()
+            .map_err(|_| DbrError::PoisonError)?
+            .clear();
+        self.records
+            .lock()
+            .map_err(|_| DbrError::PoisonError)?
+            .clear();
+        Ok(())
+    }
+
+    pub async fn commit(&self) -> Result<(), DbrError> {
+        for transaction in self.take_transactions()? {
+            if let Some(transaction) = transaction.lock().await.take() {
+                transaction.commit().await?;
+            }
+        }
+
+        let pending = {
+            let mut pending = self.pending.lock().map_err(|_| DbrError::PoisonError)?;
+            std::mem::take(&mut *pending)
+        };
+
+        for write in pending {
+            write()?;
+        }
+
+        self.clear_records()
+    }
+
+    pub async fn rollback(&self) -> Result<(), DbrError> {
+        // Nothing made it to the database, so the cache shouldn't see it either.
+        self.pending
+            .lock()
+            .map_err(|_| DbrError::PoisonError)?
+            .clear();
+        self.clear_records()?;
+
+        for transaction in self.take_transactions()? {
+            if let Some(transaction) = transaction.lock().await.take() {
+                transaction.rollback().await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,345 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sqlx::any::{Any, AnyTypeInfo, AnyValueRef};
+use sqlx::database::HasArguments;
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, Type};
+
+use crate::prelude::*;
+
+/// Translators from `dbr_fields.trans_id`, with the same ids as the original DBR.
+///
+/// These give the plain value stored in the database a richer type, use the matching
+/// type for the field in a `DbrTable` struct to get and set it as such. Or keep the plain
+/// type for the field and mark it with `#[translate(Dollars)]`, then only the generated getter
+/// and setter deal in the richer type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Translator {
+    Enum,
+    Dollars,
+    UnixTime,
+    Percent,
+}
+
+impl Translator {
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            1 => Some(Self::Enum),
+            2 => Some(Self::Dollars),
+            3 => Some(Self::UnixTime),
+            4 => Some(Self::Percent),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::Enum => 1,
+            Self::Dollars => 2,
+            Self::UnixTime => 3,
+            Self::Percent => 4,
+        }
+    }
+}
+
+/// Rust side of a translator.
+pub trait Translated: Sized {
+    const TRANSLATOR: Translator;
+
+    /// What the database actually stores.
+    type Raw;
+
+    fn from_raw(raw: Self::Raw) -> Result<Self, DbrError>;
+    fn into_raw(self) -> Self::Raw;
+}
+
+/// Value of a `#[translate(..)]` field, for the getters generated by the `DbrTable` derive.
+pub fn from_field<T, R>(raw: R) -> Result<T, DbrError>
+where
+    T: Translated,
+    R: TryInto<T::Raw> + fmt::Debug + Clone,
+{
+    match raw.clone().try_into() {
+        Ok(raw) => T::from_raw(raw),
+        Err(_) => Err(DbrError::InvalidTranslation {
+            translator: T::TRANSLATOR,
+            value: format!("{:?}", raw),
+        }),
+    }
+}
+
+/// Plain value to store in a `#[translate(..)]` field, for the setters generated by the `DbrTable` derive.
+pub fn into_field<T, R>(value: T) -> Result<R, DbrError>
+where
+    T: Translated,
+    T::Raw: fmt::Debug + Clone,
+    R: FromRaw<T::Raw>,
+{
+    let raw = value.into_raw();
+    R::try_from_raw(raw.clone()).ok_or_else(|| DbrError::InvalidTranslation {
+        translator: T::TRANSLATOR,
+        value: format!("{:?}", raw),
+    })
+}
+
+/// Types a `#[translate(..)]` field can be stored as, `None` if the raw value doesn't fit.
+///
+/// Mostly `TryFrom`, which has no `f32` from `f64` for the `float` columns of `Percent` fields.
+pub trait FromRaw<Raw>: Sized {
+    fn try_from_raw(raw: Raw) -> Option<Self>;
+}
+
+macro_rules! from_raw_int {
+    ($($ty:ty),*) => {
+        $(
+            impl FromRaw<i64> for $ty {
+                fn try_from_raw(raw: i64) -> Option<Self> {
+                    raw.try_into().ok()
+                }
+            }
+        )*
+    };
+}
+
+from_raw_int!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl FromRaw<f64> for f64 {
+    fn try_from_raw(raw: f64) -> Option<Self> {
+        Some(raw)
+    }
+}
+
+impl FromRaw<f64> for f32 {
+    fn try_from_raw(raw: f64) -> Option<Self> {
+        let narrowed = raw as f32;
+        if narrowed.is_finite() || !raw.is_finite() {
+            Some(narrowed)
+        } else {
+            None
+        }
+    }
+}
+
+// Translated values go to and from the database as their raw value.
+macro_rules! translated_type {
+    ($ty:ty) => {
+        impl Type<Any> for $ty {
+            fn type_info() -> AnyTypeInfo {
+                <<$ty as Translated>::Raw as Type<Any>>::type_info()
+            }
+
+            fn compatible(ty: &AnyTypeInfo) -> bool {
+                <<$ty as Translated>::Raw as Type<Any>>::compatible(ty)
+            }
+        }
+
+        impl<'q> Encode<'q, Any> for $ty {
+            fn encode_by_ref(&self, buf: &mut <Any as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+                <<$ty as Translated>::Raw as Encode<'q, Any>>::encode(self.clone().into_raw(), buf)
+            }
+        }
+
+        impl<'r> Decode<'r, Any> for $ty {
+            fn decode(value: AnyValueRef<'r>) -> Result<Self, BoxDynError> {
+                let raw = <<$ty as Translated>::Raw as Decode<'r, Any>>::decode(value)?;
+                Ok(<$ty as Translated>::from_raw(raw)?)
+            }
+        }
+    };
+}
+
+/// Value of an enum field, look up its handle and name with `Metadata::lookup_enum`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Enum(u32);
+
+impl Enum {
+    pub fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<&EnumOption> for Enum {
+    fn from(option: &EnumOption) -> Self {
+        Self(option.id)
+    }
+}
+
+impl Translated for Enum {
+    const TRANSLATOR: Translator = Translator::Enum;
+    type Raw = i64;
+
+    fn from_raw(raw: i64) -> Result<Self, DbrError> {
+        u32::try_from(raw)
+            .map(Self)
+            .map_err(|_| DbrError::InvalidTranslation {
+                translator: Self::TRANSLATOR,
+                value: raw.to_string(),
+            })
+    }
+
+    fn into_raw(self) -> i64 {
+        self.0 as i64
+    }
+}
+
+translated_type!(Enum);
+
+/// Money, stored as a whole number of cents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Dollars(i64);
+
+impl Dollars {
+    pub fn from_cents(cents: i64) -> Self {
+        Self(cents)
+    }
+
+    pub fn cents(&self) -> i64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Dollars {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{}${}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+impl Translated for Dollars {
+    const TRANSLATOR: Translator = Translator::Dollars;
+    type Raw = i64;
+
+    fn from_raw(raw: i64) -> Result<Self, DbrError> {
+        Ok(Self(raw))
+    }
+
+    fn into_raw(self) -> i64 {
+        self.0
+    }
+}
+
+translated_type!(Dollars);
+
+/// Point in time, stored as seconds since the unix epoch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct UnixTime(i64);
+
+impl UnixTime {
+    pub fn from_seconds(seconds: i64) -> Self {
+        Self(seconds)
+    }
+
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    pub fn seconds(&self) -> i64 {
+        self.0
+    }
+}
+
+impl From<SystemTime> for UnixTime {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => Self(since.as_secs() as i64),
+            Err(err) => Self(-(err.duration().as_secs() as i64)),
+        }
+    }
+}
+
+impl From<UnixTime> for SystemTime {
+    fn from(time: UnixTime) -> Self {
+        let offset = Duration::from_secs(time.0.unsigned_abs());
+        if time.0 < 0 {
+            UNIX_EPOCH - offset
+        } else {
+            UNIX_EPOCH + offset
+        }
+    }
+}
+
+impl Translated for UnixTime {
+    const TRANSLATOR: Translator = Translator::UnixTime;
+    type Raw = i64;
+
+    fn from_raw(raw: i64) -> Result<Self, DbrError> {
+        Ok(Self(raw))
+    }
+
+    fn into_raw(self) -> i64 {
+        self.0
+    }
+}
+
+translated_type!(UnixTime);
+
+/// Percentage, stored as the percent itself so `12.5` is 12.5%.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default)]
+pub struct Percent(f64);
+
+impl Percent {
+    pub fn new(percent: f64) -> Self {
+        Self(percent)
+    }
+
+    pub fn percent(&self) -> f64 {
+        self.0
+    }
+
+    /// As a fraction, `0.125` for 12.5%.
+    pub fn ratio(&self) -> f64 {
+        self.0 / 100.0
+    }
+}
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl Translated for Percent {
+    const TRANSLATOR: Translator = Translator::Percent;
+    type Raw = f64;
+
+    fn from_raw(raw: f64) -> Result<Self, DbrError> {
+        Ok(Self(raw))
+    }
+
+    fn into_raw(self) -> f64 {
+        self.0
+    }
+}
+
+translated_type!(Percent);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percents_fit_float_fields() {
+        let stored: f32 = into_field(Percent::new(12.5)).unwrap();
+        assert_eq!(stored, 12.5);
+        assert_eq!(
+            from_field::<Percent, _>(stored).unwrap(),
+            Percent::new(12.5)
+        );
+
+        assert!(into_field::<Percent, f32>(Percent::new(1e300)).is_err());
+    }
+
+    #[test]
+    fn enums_have_to_fit_the_field() {
+        let stored: u8 = into_field(Enum::new(200)).unwrap();
+        assert_eq!(stored, 200);
+        assert!(into_field::<Enum, u8>(Enum::new(300)).is_err());
+    }
+}
@@ -0,0 +1,343 @@
+use std::fmt;
+
+use crate::prelude::*;
+
+/// Value of a field as far as validation is concerned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Text(String),
+    Bytes(usize),
+    Enum(Enum),
+
+    /// Some type we don't know how to look into, only nullability gets checked.
+    Unchecked,
+}
+
+/// How a field's value is checked, implement this for your own field types to have them validated.
+pub trait ToFieldValue {
+    fn to_field_value(&self) -> FieldValue;
+}
+
+macro_rules! to_field_value {
+    ($variant:ident as $as:ty: $($ty:ty),*) => {
+        $(
+            impl ToFieldValue for $ty {
+                fn to_field_value(&self) -> FieldValue {
+                    FieldValue::$variant(*self as $as)
+                }
+            }
+        )*
+    };
+}
+
+to_field_value!(Int as i64: i8, i16, i32, i64, isize);
+to_field_value!(UInt as u64: u8, u16, u32, u64, usize, bool);
+to_field_value!(Float as f64: f32, f64);
+
+impl ToFieldValue for String {
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Text(self.clone())
+    }
+}
+
+impl ToFieldValue for &str {
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Text(self.to_string())
+    }
+}
+
+impl ToFieldValue for Vec<u8> {
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Bytes(self.len())
+    }
+}
+
+impl<T: ToFieldValue> ToFieldValue for Option<T> {
+    fn to_field_value(&self) -> FieldValue {
+        match self {
+            Some(value) => value.to_field_value(),
+            None => FieldValue::Null,
+        }
+    }
+}
+
+impl ToFieldValue for Enum {
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Enum(*self)
+    }
+}
+
+impl ToFieldValue for Dollars {
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Int(self.cents())
+    }
+}
+
+impl ToFieldValue for UnixTime {
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Int(self.seconds())
+    }
+}
+
+impl ToFieldValue for Percent {
+    fn to_field_value(&self) -> FieldValue {
+        FieldValue::Float(self.percent())
+    }
+}
+
+// Used by the `DbrTable` derive so fields of types without `ToFieldValue` still compile,
+// `(&Probe(&value)).field_value()` picks the `ToFieldValue` impl when there is one and
+// falls back to `FieldValue::Unchecked` through the extra reference otherwise.
+#[doc(hidden)]
+pub struct Probe<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait ViaToFieldValue {
+    fn field_value(&self) -> FieldValue;
+}
+
+impl<'a, T: ToFieldValue> ViaToFieldValue for Probe<'a, T> {
+    fn field_value(&self) -> FieldValue {
+        self.0.to_field_value()
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaUnchecked {
+    fn field_value(&self) -> FieldValue;
+}
+
+impl<'a, T> ViaUnchecked for &Probe<'a, T> {
+    fn field_value(&self) -> FieldValue {
+        FieldValue::Unchecked
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationReason {
+    Null,
+    Negative,
+    OutOfRange { min: i128, max: i128 },
+    TooLong { max: u64, length: usize },
+    PatternMismatch(String),
+    UnknownEnum(u32),
+}
+
+impl fmt::Display for ValidationReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "can't be null"),
+            Self::Negative => write!(f, "can't be negative"),
+            Self::OutOfRange { min, max } => write!(f, "has to be between {} and {}", min, max),
+            Self::TooLong { max, length } => {
+                write!(f, "is {} long, at most {} is allowed", length, max)
+            }
+            Self::PatternMismatch(pattern) => write!(f, "doesn't match /{}/", pattern),
+            Self::UnknownEnum(id) => write!(f, "{} is not one of the enum options", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationFailure {
+    pub field: String,
+    pub reason: ValidationReason,
+}
+
+impl fmt::Display for ValidationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.field, self.reason)
+    }
+}
+
+impl Metadata {
+    /// Check the values about to be written to a `T` against its field metadata.
+    ///
+    /// Every failing field is collected into a single `DbrError::Validation`, fields
+    /// the metadata doesn't know about are left for the database to complain about.
+    pub fn validate<T: DbrTable>(&self, values: &[(&str, FieldValue)]) -> Result<(), DbrError> {
+        let table = self.lookup_dbr_table::<T>()?;
+
+        let mut failures = Vec::new();
+        for (name, value) in values {
+            let field = match table.fields.get(*name) {
+                Some(field_id) => self.lookup_field(*field_id)?,
+                None => continue,
+            };
+
+            if let Some(reason) = self.validate_field(field, value) {
+                failures.push(ValidationFailure {
+                    field: field.name.clone(),
+                    reason,
+                });
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(DbrError::Validation {
+                table: table.name.clone(),
+                failures,
+            })
+        }
+    }
+
+    fn validate_field(&self, field: &Field, value: &FieldValue) -> Option<ValidationReason> {
+        let kind = field.kind();
+        match value {
+            FieldValue::Null if !field.is_nullable => return Some(ValidationReason::Null),
+            FieldValue::Int(value) if !field.is_signed && *value < 0 => {
+                return Some(ValidationReason::Negative)
+            }
+            FieldValue::Float(value) if !field.is_signed && *value < 0.0 => {
+                return Some(ValidationReason::Negative)
+            }
+            _ => {}
+        }
+
+        let integer = match value {
+            FieldValue::Int(value) => Some(*value as i128),
+            FieldValue::UInt(value) => Some(*value as i128),
+            _ => None,
+        };
+
+        if let (Some(integer), Some(bits)) = (integer, kind.and_then(|kind| kind.bits())) {
+            let (min, max) = match (bits, field.is_signed) {
+                (1, _) => (0, 1),
+                (bits, true) => (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1),
+                (bits, false) => (0, (1i128 << bits) - 1),
+            };
+
+            if integer < min || integer > max {
+                return Some(ValidationReason::OutOfRange { min, max });
+            }
+        }
+
+        // `max_value` is the column width for these, anything else it is only informational.
+        let sized = matches!(
+            kind,
+            Some(DataType::VarChar | DataType::Char | DataType::Binary | DataType::VarBinary)
+        );
+        let length = match value {
+            FieldValue::Text(text) => Some(text.chars().count()),
+            FieldValue::Bytes(length) => Some(*length),
+            _ => None,
+        };
+
+        if let Some(length) = length {
+            if sized && field.max_value > 0 && length as u64 > field.max_value {
+                return Some(ValidationReason::TooLong {
+                    max: field.max_value,
+                    length,
+                });
+            }
+        }
+
+        if let FieldValue::Enum(value) = value {
+            let known = self
+                .enums
+                .get(&field.id)
+                .map(|options| options.iter().any(|option| option.id == value.id()));
+
+            if known == Some(false) {
+                return Some(ValidationReason::UnknownEnum(value.id()));
+            }
+        }
+
+        let text = match value {
+            FieldValue::Text(text) => Some(text.clone()),
+            FieldValue::Int(value) => Some(value.to_string()),
+            FieldValue::UInt(value) => Some(value.to_string()),
+            FieldValue::Float(value) => Some(value.to_string()),
+            _ => None,
+        };
+
+        if let (Some(pattern), Some(text)) = (self.patterns.get(&field.id), text) {
+            if !pattern.is_match(&text) {
+                return Some(ValidationReason::PatternMismatch(
+                    pattern.as_str().to_owned(),
+                ));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(name: &str, value: FieldValue) -> Option<ValidationReason> {
+        let metadata = Metadata::for_tests();
+        let table = metadata.tables.values().next().unwrap();
+        let field = &metadata.fields[&table.fields[name]];
+        metadata.validate_field(field, &value)
+    }
+
+    #[test]
+    fn rejects_null_in_non_nullable_fields() {
+        assert_eq!(
+            reason("plays", FieldValue::Null),
+            Some(ValidationReason::Null)
+        );
+        assert_eq!(reason("name", FieldValue::Null), None);
+    }
+
+    #[test]
+    fn checks_integers_against_the_field_width() {
+        assert_eq!(
+            reason("id", FieldValue::Int(-1)),
+            Some(ValidationReason::Negative)
+        );
+        assert_eq!(
+            reason("plays", FieldValue::Int(40_000)),
+            Some(ValidationReason::OutOfRange {
+                min: -32768,
+                max: 32767
+            })
+        );
+        assert_eq!(reason("plays", FieldValue::Int(-32768)), None);
+    }
+
+    #[test]
+    fn checks_text_against_the_column_width() {
+        assert_eq!(
+            reason("name", FieldValue::Text("Sgt Peppers Lonely".into())),
+            Some(ValidationReason::TooLong {
+                max: 16,
+                length: 18
+            })
+        );
+    }
+
+    #[test]
+    fn checks_text_against_the_field_regex() {
+        assert_eq!(reason("name", FieldValue::Text("Help".into())), None);
+        assert_eq!(
+            reason("name", FieldValue::Text("Help!".into())),
+            Some(ValidationReason::PatternMismatch("^[A-Za-z ]+$".into()))
+        );
+    }
+
+    #[test]
+    fn skips_invalid_regexes() {
+        let mut metadata = Metadata::for_tests();
+        let table = metadata.tables.values().next().unwrap().clone();
+        let field_id = table.fields["name"];
+        metadata.fields.get_mut(&field_id).unwrap().regex = Some("(".to_owned());
+        metadata.rebuild();
+
+        let field = &metadata.fields[&field_id];
+        assert!(!metadata.patterns.contains_key(&field_id));
+        assert_eq!(
+            metadata.validate_field(field, &FieldValue::Text("Help!".into())),
+            None
+        );
+    }
+}